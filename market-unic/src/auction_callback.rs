@@ -7,6 +7,9 @@ use crate::*;
 #[serde(crate = "near_sdk::serde")]
 pub struct SaleArgs {
     pub sale_conditions: SalePriceInYoctoNear,
+    //optional bid increment and reserve price of the auction
+    #[serde(flatten)]
+    pub auction_args: AuctionArgs,
 }
 
 trait NftAuctionReceiver {
@@ -42,85 +45,52 @@ impl NftAuctionReceiver for Contract {
         );
 
         assert_eq!(account_id, signer_id, "owner_id should be signer_id");
+        //make sure the market accepts the nft contract for auctions
+        self.assert_collection_allows(&nft_contract_id, ListingType::Auction);
 
-        let storage_amount = self.storage_minimum_balance().0;
-        //get the total storage paid by the owner
-        let owner_paid_storage = self.storage_deposits.get(&signer_id).unwrap_or(0);
+        //we need to enforce that the user has enough storage for 1 EXTRA sale.
+        self.internal_assert_storage(&signer_id, 1);
 
-        let signer_storage_required =
-            (self.get_supply_by_owner_id(signer_id).0 + 1) as u128 * storage_amount;
-
-        assert!(
-            owner_paid_storage >= signer_storage_required,
-            "Insufficient storage paid: {}, for {} sales at {} rate of per sale",
-            owner_paid_storage,
-            signer_storage_required / STORAGE_PER_SALE,
-            STORAGE_PER_SALE
-        );
-
-        let SaleArgs { sale_conditions } =
+        let SaleArgs {
+            sale_conditions,
+            auction_args,
+        } =
             //the sale conditions come from the msg field. The market assumes that the user passed
             //in a proper msg. If they didn't, it panics. 
             near_sdk::serde_json::from_str(&msg).expect("Not valid SaleArgs");
 
-        let contract_and_auction_token_id =
-            format!("{}{}{}", nft_contract_id, DELIMETER, auction_token);
-
-        self.auctions.insert(
-            &contract_and_auction_token_id,
-            &Auction {
-                owner_id: account_id.clone(),                 //owner of the sale / token
-                auction_id, //approval ID for that token that was given to the market
-                nft_contract_id: nft_contract_id.to_string(), //NFT contract the token was minted on
-                auction_token: auction_token.clone(), //the actual token ID
-                sale_conditions, //the sale conditions
-                start_time: (start_time as u128) * (1_000_000_000 as u128),
-                end_time: (end_time as u128) * (1_000_000_000 as u128),
-                winner: None,
-                is_near_claimed: false,
-                is_nft_claimed: false,
-            },
-        );
-
-        //get the auctions by owner ID for the given owner. If there are none, we create a new empty set
-        let mut by_auction_owner_id =
-            self.by_auction_owner_id
-                .get(&account_id)
-                .unwrap_or_else(|| {
-                    UnorderedSet::new(
-                        StorageKey::ByAuctionOwnerIdInner {
-                            //we get a new unique prefix for the collection by hashing the owner account
-                            account_id_hash: hash_account_id(&account_id),
-                        }
-                        .try_to_vec()
-                        .unwrap(),
-                    )
-                });
-
-        by_auction_owner_id.insert(&contract_and_auction_token_id);
-
-        self.by_auction_owner_id
-            .insert(&account_id, &by_auction_owner_id);
-
-        //get the auction token IDs for the given nft contract ID. If there are none, we create a new empty set
-        let mut auctions_by_nft_contract_id = self
-            .auctions_by_nft_contract_id
-            .get(&nft_contract_id)
-            .unwrap_or_else(|| {
-                UnorderedSet::new(
-                    StorageKey::AuctionsByNFTContractIdInner {
-                        //we get a new unique prefix for the collection by hashing the owner
-                        account_id_hash: hash_account_id(&nft_contract_id),
-                    }
-                    .try_to_vec()
-                    .unwrap(),
-                )
-            });
-
-        //insert the token ID into the set
-        auctions_by_nft_contract_id.insert(&auction_token);
-
-        self.auctions_by_nft_contract_id
-            .insert(&nft_contract_id, &auctions_by_nft_contract_id);
+        //insert the auction and populate the collections necessary for the view calls
+        self.internal_add_auction(Auction {
+            owner_id: account_id,                         //owner of the sale / token
+            auction_id, //approval ID for that token that was given to the market
+            nft_contract_id: nft_contract_id.to_string(), //NFT contract the token was minted on
+            auction_token, //the actual token ID
+            sale_conditions, //the sale conditions
+            bid_escrow: U128(0), //nothing is escrowed until the first bid
+            start_time: (start_time as u128) * (1_000_000_000 as u128),
+            end_time: (end_time as u128) * (1_000_000_000 as u128),
+            winner: None,
+            is_near_claimed: false,
+            is_nft_claimed: false,
+            proceeds: None,
+            escrowed: false,
+            min_bid_increment: auction_args.min_bid_increment,
+            reserve_price: auction_args.reserve_price,
+            extension: auction_args
+                .extension
+                .map(|extension| extension.into_auction_extension(end_time)),
+            extensions: vec![],
+            no_bid_fallback: auction_args
+                .no_bid_fallback
+                .map(|no_bid_fallback| no_bid_fallback.into_nanoseconds(false)),
+            sealed_bid: auction_args
+                .sealed_bid
+                .map(|sealed_bid| sealed_bid.into_sealed_bid_config(end_time)),
+            settlement: auction_args
+                .settlement
+                .unwrap_or(AuctionSettlement::HighestBid),
+            second_bid: None,
+            buy_now_price: auction_buy_now_price(sale_conditions, auction_args.buy_now_price),
+        });
     }
 }
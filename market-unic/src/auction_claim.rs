@@ -0,0 +1,155 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    //lets the winner claim the token once the auction is over. This triggers the nft transfer and once the
+    //transfer is confirmed in the callback, the seller and royalty holders can claim their proceeds
+    pub fn claim_auction_nft(&mut self, nft_contract_id: AccountId, token_id: String) -> Promise {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+
+        let mut auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        assert!(
+            env::block_timestamp() > auction_settles_at(&auction),
+            "The auction is not over yet"
+        );
+        assert_eq!(
+            auction.winner,
+            Some(env::predecessor_account_id()),
+            "Only the winner can claim the NFT"
+        );
+        assert!(!auction.is_nft_claimed, "NFT is already claimed");
+        assert!(auction_reserve_met(&auction), "Reserve price was not met");
+
+        //only the clearing price stays escrowed for the payout
+        self.internal_refund_above_clearing_price(&mut auction);
+
+        //mark the NFT as claimed while the transfer is in flight so it can't be claimed twice.
+        //if the transfer fails, the callback lets the winner claim it again
+        auction.is_nft_claimed = true;
+        self.auctions
+            .insert(&contract_and_auction_token_id, &auction);
+
+        ext_contract::nft_transfer_payout(
+            env::predecessor_account_id(),
+            token_id.clone(),
+            auction.auction_id,
+            "payout from market".to_string(),
+            auction.bid_escrow,
+            10,
+            nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        //confirm the transfer and record the proceeds every account in the payout can claim
+        .then(ext_self::resolve_auction_nft_claim(
+            nft_contract_id,
+            token_id,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_PURCHASE,
+        ))
+    }
+
+    //lets the seller or a royalty holder claim their share of the top bid once the NFT transfer was confirmed.
+    //returns the amount that was paid out. The auction is removed once every share is claimed
+    pub fn claim_auction_proceeds(&mut self, nft_contract_id: AccountId, token_id: String) -> U128 {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+
+        let mut auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        let account_id = env::predecessor_account_id();
+        let mut proceeds = auction
+            .proceeds
+            .expect("The NFT transfer hasn't been confirmed yet");
+        let amount = proceeds.remove(&account_id).expect("No proceeds to claim");
+
+        self.internal_release_escrow(amount.0);
+        Promise::new(account_id).transfer(amount.0);
+
+        //once every account claimed their share, the NEAR side is done
+        auction.is_near_claimed = proceeds.is_empty();
+        auction.proceeds = Some(proceeds);
+        self.internal_update_auction_claims(nft_contract_id, token_id, auction);
+
+        amount
+    }
+
+    //private method used to resolve the NFT claim. Returns true if the transfer went through
+    #[private]
+    pub fn resolve_auction_nft_claim(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> bool {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        let price = auction.bid_escrow;
+
+        // checking for payout information returned from the nft_transfer_payout method
+        let payout = if let Some(payout) = verify_payout(price, &auction.owner_id) {
+            payout
+        //if the transfer failed, the winner can claim the NFT again. The proceeds stay locked until it goes through
+        } else {
+            env::log_str("NFT transfer failed, the NFT can be claimed again");
+            auction.is_nft_claimed = false;
+            self.auctions
+                .insert(&contract_and_auction_token_id, &auction);
+            return false;
+        };
+
+        //take the market commission before the proceeds are recorded. The commission leaves the escrow for the treasury
+        let payout =
+            self.internal_take_market_fee(&nft_contract_id, &near_currency(), price, payout);
+        let distributed: Balance = payout.values().map(|amount| amount.0).sum();
+        self.internal_release_escrow(price.0 - distributed);
+
+        //accounts with nothing to claim are left out of the proceeds
+        let proceeds: HashMap<AccountId, U128> = payout
+            .into_iter()
+            .filter(|(_, amount)| amount.0 > 0)
+            .collect();
+
+        //if nobody has anything to claim, the NEAR side is done right away
+        auction.is_near_claimed = proceeds.is_empty();
+        auction.proceeds = Some(proceeds);
+        self.internal_update_auction_claims(nft_contract_id, token_id, auction);
+
+        true
+    }
+}
+
+impl Contract {
+    //stores the claim progress of an auction. The auction is removed once both the NFT and the proceeds were claimed
+    fn internal_update_auction_claims(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        auction: Auction,
+    ) {
+        if auction.is_nft_claimed && auction.is_near_claimed {
+            self.internal_remove_auction(nft_contract_id, token_id);
+        } else {
+            let contract_and_auction_token_id =
+                format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+            self.auctions
+                .insert(&contract_and_auction_token_id, &auction);
+        }
+    }
+}
+
+//this is the cross contract call that we call on our own contract.
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn resolve_auction_nft_claim(&mut self, nft_contract_id: AccountId, token_id: String) -> bool;
+}
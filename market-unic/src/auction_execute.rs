@@ -1,5 +1,5 @@
 use crate::*;
-use near_sdk::promise_result_as_success;
+use near_sdk::PromiseResult;
 
 //struct that holds important information about each sale on the market
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
@@ -15,16 +15,190 @@ pub struct Auction {
     pub auction_token: String,
     //sale price in yoctoNEAR that the token is listed for
     pub sale_conditions: SalePriceInYoctoNear,
+    //exact amount in yoctoNEAR the market holds for the winning bid (0 if there is no bid)
+    pub bid_escrow: SalePriceInYoctoNear,
     pub start_time: u128,
     pub end_time: u128,
     pub winner: Option<AccountId>,
+    //whether every account in the payout claimed its proceeds
     pub is_near_claimed: bool,
+    //whether the winner claimed the NFT (set while the transfer is in flight and reset if it fails)
     pub is_nft_claimed: bool,
+    //proceeds (after the market commission) each account can claim once the NFT transfer was confirmed
+    pub proceeds: Option<HashMap<AccountId, U128>>,
+    //whether the market holds the token in custody (listed through nft_transfer_call)
+    pub escrowed: bool,
+    //how much a bid must at least raise the current winning bid by (defaults to 1 yoctoNEAR)
+    pub min_bid_increment: Option<BidIncrement>,
+    //the lowest winning bid the seller accepts. It is kept out of the views so bidders can't see it
+    #[serde(skip_serializing, default)]
+    pub reserve_price: Option<SalePriceInYoctoNear>,
+    //anti-sniping configuration. Late bids push the end time back
+    pub extension: Option<AuctionExtension>,
+    //every time the end time was pushed back by a late bid
+    pub extensions: Vec<AuctionExtensionRecord>,
+    //what happens to the token if the auction ends without any bid
+    pub no_bid_fallback: Option<NoBidFallback>,
+    //if the auction is sealed-bid, the reveal deadline and what happens to unrevealed bids
+    pub sealed_bid: Option<SealedBidConfig>,
+    //what the winner pays when the auction is settled
+    pub settlement: AuctionSettlement,
+    //second highest bid in yoctoNEAR (the starting price until the winner is outbid)
+    pub second_bid: Option<SalePriceInYoctoNear>,
+    //price in yoctoNEAR any buyer can pay to end the auction early, until a bid reaches it
+    pub buy_now_price: Option<SalePriceInYoctoNear>,
+}
+
+//struct for keeping track of an auction purchase while the nft contract transfers the token
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionPurchase {
+    pub buyer_id: AccountId,
+    pub seller_id: AccountId,
+    //price in yoctoNEAR the market holds for the purchase
+    pub price: U128,
+    //nft contract where the token was minted
+    pub nft_contract_id: AccountId,
+    pub token_id: String,
+    //whether the market holds the token in custody
+    pub escrowed: bool,
+}
+
+//what the winner of an auction pays. Either their own bid or the second highest bid plus the increment
+//(never more than their own bid). The difference with their bid is refunded at settlement
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum AuctionSettlement {
+    HighestBid,
+    SecondPrice,
+}
+
+//what happens to the token of an auction that ends without any bid. The auction can be relisted
+//once for a new duration (passed in seconds, stored in nanoseconds) or turned into a fixed price sale
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum NoBidFallback {
+    Relist {
+        duration: u64,
+    },
+    FixedPrice {
+        sale_conditions: SalePriceInYoctoNear,
+    },
+}
+
+impl NoBidFallback {
+    //validates the fallback passed in the listing msg and converts the duration to nanoseconds.
+    //only auctions held in escrow can be relisted since the auction approval of the nft contract can't be moved
+    pub fn into_nanoseconds(self, escrowed: bool) -> Self {
+        match self {
+            NoBidFallback::Relist { duration } => {
+                assert!(
+                    escrowed,
+                    "Only auctions held in escrow can be relisted, approve a new auction instead"
+                );
+                assert!(duration > 0, "Relist duration must be greater than 0");
+                NoBidFallback::Relist {
+                    duration: duration * 1_000_000_000,
+                }
+            }
+            fixed_price => fixed_price,
+        }
+    }
+}
+
+//struct that holds the anti-sniping configuration of an auction (times are in nanoseconds)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionExtension {
+    //a bid placed less than this long before the end extends the auction
+    pub window: u64,
+    //how long the end time is pushed back by
+    pub extension: u64,
+    //the end time can never be pushed back past this time
+    pub max_end_time: Option<u64>,
+}
+
+//struct for the anti-sniping configuration passed in the listing msg. The window and extension
+//are in minutes and the hard cap on the end time is in seconds. The hard cap defaults to (and can't be
+//later than) the end of the settlement period of the auction approval
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionExtensionArgs {
+    pub window: u64,
+    pub extension: u64,
+    pub max_end_time: Option<u64>,
+}
+
+//struct that records an extension of the end time of an auction
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionExtensionRecord {
+    //bidder whose late bid extended the auction
+    pub bidder_id: AccountId,
+    //end time (in nanoseconds) before the bid
+    pub previous_end_time: u64,
+    //end time (in nanoseconds) after the bid
+    pub end_time: u64,
+}
+
+impl AuctionExtensionArgs {
+    //validates the passed in configuration and converts it to nanoseconds
+    pub fn into_auction_extension(self, end_time: u64) -> AuctionExtension {
+        assert!(
+            self.window > 0 && self.extension > 0,
+            "Extension window and extension must be greater than 0"
+        );
+        let end_time = end_time * 1_000_000_000;
+        //late bids can't push the end past the expiry of the auction approval, otherwise the token would be
+        //unlocked on the nft contract while the bids are still escrowed
+        let latest_end_time = end_time + AUCTION_SETTLEMENT_PERIOD;
+        let max_end_time = self
+            .max_end_time
+            .map(|max_end_time| max_end_time * 1_000_000_000)
+            .unwrap_or(latest_end_time);
+        assert!(
+            max_end_time >= end_time,
+            "The hard cap cannot be before the end time"
+        );
+        assert!(
+            max_end_time <= latest_end_time,
+            "The hard cap cannot be after the settlement period of the auction"
+        );
+
+        AuctionExtension {
+            window: self.window * 60 * 1_000_000_000,
+            extension: self.extension * 60 * 1_000_000_000,
+            max_end_time: Some(max_end_time),
+        }
+    }
+}
+
+//the minimum raise over the current winning bid, either an absolute amount in yoctoNEAR
+//or a share of the current winning bid in basis points
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum BidIncrement {
+    Absolute(U128),
+    BasisPoints(u32),
+}
+
+//struct for the auction configuration that can be passed in the listing msg next to the sale conditions
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionArgs {
+    pub min_bid_increment: Option<BidIncrement>,
+    pub reserve_price: Option<SalePriceInYoctoNear>,
+    pub extension: Option<AuctionExtensionArgs>,
+    pub no_bid_fallback: Option<NoBidFallback>,
+    pub sealed_bid: Option<SealedBidArgs>,
+    pub settlement: Option<AuctionSettlement>,
+    pub buy_now_price: Option<SalePriceInYoctoNear>,
 }
 
 #[near_bindgen]
 impl Contract {
-    //removes a auction from the market.
+    //removes a auction from the market. The seller can only take an auction down before it starts or
+    //before anyone bid on it, so a live auction stays locked for its bidders
     #[payable]
     pub fn remove_auction(&mut self, nft_contract_id: AccountId, token_id: String) {
         //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
@@ -38,17 +212,31 @@ impl Contract {
             .get(&contract_and_auction_token_id)
             .expect("No Auction");
 
-        if !(auction.winner == None) {
-            let auctionWinnerId = auction.winner.map(|a| a.into()).unwrap();
-            let old_winner = Promise::new(auctionWinnerId);
-            old_winner.transfer(auction.sale_conditions.0 - ENROLL_FEE);
-        }
+        //bids (including committed sealed bids) can only be placed once the auction started
+        let has_bids = auction.winner.is_some()
+            || self
+                .sealed_bids
+                .get(&contract_and_auction_token_id)
+                .map(|sealed_bids| !sealed_bids.is_empty())
+                .unwrap_or(false);
+        assert!(
+            (env::block_timestamp() as u128) < auction.start_time || !has_bids,
+            "Auction can't be removed once it has bids"
+        );
 
-        let auction = self.internal_remove_auction(contract_id, token_id.clone());
+        let auction = self.internal_remove_auction(contract_id.clone(), token_id.clone());
         //get the predecessor of the call and make sure they're the owner of the sale
         let owner_id = env::predecessor_account_id();
         //if this fails, the remove auction will revert
         assert_eq!(owner_id, auction.owner_id, "Must be auction owner");
+
+        //if the market was holding the token, we give it back to the owner. Otherwise the market
+        //gives up its auction approval so the token is unlocked on the nft contract
+        if auction.escrowed {
+            self.internal_return_escrowed_token(contract_id, token_id, owner_id);
+        } else {
+            internal_revoke_auction_approval(contract_id, token_id);
+        }
     }
 
     #[payable]
@@ -62,6 +250,12 @@ impl Contract {
             .get(&contract_and_auction_token_id)
             .expect("No Auction");
 
+        //sealed-bid auctions only take committed bids
+        assert!(
+            auction.sealed_bid.is_none(),
+            "Sealed-bid auctions take bids through commit_sealed_bid"
+        );
+
         assert_eq!(
             env::block_timestamp() > (auction.start_time) as u64,
             true,
@@ -72,10 +266,12 @@ impl Contract {
             true,
             "This auction is already done"
         );
-        assert_eq!(
-            env::attached_deposit() > auction.sale_conditions.0,
-            true,
-            "Price must be greater than current winner's price"
+        //the first bid only has to beat the starting price. Every other bid has to raise the winning bid by the increment
+        let min_bid = auction_min_bid(&auction);
+        assert!(
+            env::attached_deposit() >= min_bid,
+            "Bid must be greater than or equal to {:?}",
+            min_bid
         );
 
         //get the buyer ID which is the person who called the function and make sure they're not the owner of the auction.
@@ -85,24 +281,41 @@ impl Contract {
             "Cannot bid on your own auction."
         );
 
-        if !(auction.winner == None) {
-            let auctionWinnerId = auction.winner.map(|a| a.into()).unwrap();
-            let old_winner = Promise::new(auctionWinnerId);
-            old_winner.transfer(auction.sale_conditions.0 - ENROLL_FEE);
+        //the outbid winner gets their full bid back and their bid becomes the second highest
+        if let Some(old_winner_id) = auction.winner {
+            self.internal_release_escrow(auction.bid_escrow.0);
+            Promise::new(old_winner_id).transfer(auction.bid_escrow.0);
         }
+        auction.second_bid = Some(auction.sale_conditions);
         auction.winner = Some(env::predecessor_account_id());
         auction.sale_conditions.0 = env::attached_deposit();
+        //the market holds the whole deposit for the new winner
+        auction.bid_escrow = U128(env::attached_deposit());
+        self.internal_lock_escrow(auction.bid_escrow.0);
+
+        //record the bid in the history of the auction
+        self.internal_record_bid(Bid {
+            bidder_id: buyer_id,
+            nft_contract_id: auction.nft_contract_id.clone(),
+            token_id: auction.auction_token.clone(),
+            amount: auction.bid_escrow,
+            placed_at: env::block_timestamp(),
+        });
+
+        //a bid placed close to the end pushes the end time back so other bidders can respond
+        extend_auction(&mut auction);
+
         self.auctions
             .insert(&contract_and_auction_token_id, &auction);
     }
 
-    #[payable]
+    //settles an auction once it's over by selling the token to the winner for the escrowed top bid.
+    //anyone can call this (the seller, the winner or a keeper bot) and nothing has to be attached
     pub fn process_auction_purchase(
         &mut self,
         nft_contract_id: AccountId,
         token_id: String,
     ) -> Promise {
-        let deposit = env::attached_deposit();
         let contract_id: AccountId = nft_contract_id.into();
         let contract_and_auction_token_id = format!("{}{}{}", contract_id, DELIMETER, token_id);
 
@@ -111,13 +324,8 @@ impl Contract {
             .get(&contract_and_auction_token_id)
             .expect("No Auction");
 
-        let buyer_id: AccountId = auction.winner.map(|a| a.into()).unwrap();
-
-        let price = auction.sale_conditions;
-
-        assert_eq!(
-            env::block_timestamp() > (auction.end_time) as u64,
-            true,
+        assert!(
+            env::block_timestamp() > auction_settles_at(&auction),
             "The auction is not over yet"
         );
 
@@ -125,18 +333,21 @@ impl Contract {
 
         assert_eq!(auction.is_near_claimed, false, "NEAR already claimed N");
 
-        assert!(
-            deposit >= auction.sale_conditions.0,
-            "Attached deposit must be greater than or equal to the current price: {:?}",
-            price
-        );
+        //if the reserve wasn't met, the auction ended unsold and has to be removed with remove_unsold_auction
+        assert!(auction_reserve_met(&auction), "Reserve price was not met");
 
-        //get the auction object by removing the auction
+        //auctions without bids are ended with remove_unsold_auction
+        let buyer_id: AccountId = auction.winner.clone().expect("Auction has no bids");
+
+        //get the auction object by removing the auction. This makes sure the auction can only be settled once
         let mut auction = self.internal_remove_auction(contract_id.clone(), token_id.clone());
 
+        //the token is sold for the clearing price out of the top bid the market has been holding since offer_bid
+        let price = self.internal_refund_above_clearing_price(&mut auction);
+
         ext_contract::nft_transfer_payout(
             buyer_id.clone(),                 //purchaser (person to transfer the NFT to)
-            token_id,                         //token ID to transfer
+            token_id.clone(),                 //token ID to transfer
             auction.auction_id, //market contract's approval ID in order to transfer the token on behalf of the owner
             "payout from market".to_string(), //memo (to include some context)
             /*
@@ -145,70 +356,347 @@ impl Contract {
             */
             price,
             10,
-            contract_id,
+            contract_id.clone(),
             1,
             GAS_FOR_NFT_TRANSFER, //the maximum amount of accounts the market can payout at once (this is limited by GAS)
         )
         //after the transfer payout has been initiated, we resolve the promise by calling our own resolve_purchase function.
-        //resolve purchase will take the payout object returned from the nft_transfer_payout and actually pay the accounts
+        //resolve purchase will take the payout object returned from the nft_transfer_payout and distribute the escrow
         .then(ext_self::resolve_auction_purchase(
-            buyer_id,
-            price,
+            AuctionPurchase {
+                buyer_id,
+                seller_id: auction.owner_id,
+                price,
+                nft_contract_id: contract_id,
+                token_id,
+                escrowed: auction.escrowed,
+            },
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_PURCHASE,
+        ))
+    }
+
+    //ends an auction early by paying its buy-now price. This is possible while the auction is running and no bid
+    //has reached the buy-now price. The current top bidder is refunded and the token is sold right away
+    #[payable]
+    pub fn buy_now(&mut self, nft_contract_id: AccountId, token_id: String) -> Promise {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+
+        let auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        let buy_now_price = auction.buy_now_price.expect("Auction has no buy-now price");
+        //sealed bids are hidden so we can't know if one of them reached the buy-now price
+        assert!(
+            auction.sealed_bid.is_none(),
+            "Sealed-bid auctions can't be bought now"
+        );
+
+        let now = env::block_timestamp();
+        assert!(
+            now > auction.start_time as u64,
+            "This auction has not started"
+        );
+        assert!(
+            now < auction.end_time as u64,
+            "This auction is already done"
+        );
+        assert!(
+            auction.winner.is_none() || auction.sale_conditions.0 < buy_now_price.0,
+            "A bid already reached the buy-now price"
+        );
+
+        let buyer_id = env::predecessor_account_id();
+        assert_ne!(auction.owner_id, buyer_id, "Cannot buy your own auction.");
+
+        //make sure the deposit covers the buy-now price and refund the excess
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= buy_now_price.0,
+            "Attached deposit must be greater than or equal to the buy-now price: {:?}",
+            buy_now_price
+        );
+        if deposit > buy_now_price.0 {
+            Promise::new(buyer_id.clone()).transfer(deposit - buy_now_price.0);
+        }
+
+        //get the auction object by removing the auction. This makes sure the auction can only be settled once
+        let auction = self.internal_remove_auction(nft_contract_id.clone(), token_id.clone());
+
+        //the current top bidder gets their full bid back
+        if let Some(winner_id) = auction.winner {
+            self.internal_release_escrow(auction.bid_escrow.0);
+            Promise::new(winner_id).transfer(auction.bid_escrow.0);
+        }
+
+        //the buy-now price is escrowed until it's paid out (or refunded) by resolve_auction_purchase
+        self.internal_lock_escrow(buy_now_price.0);
+
+        ext_contract::nft_transfer_payout(
+            buyer_id.clone(),
+            token_id.clone(),
+            auction.auction_id,
+            "payout from market".to_string(),
+            buy_now_price,
+            10,
+            nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        //resolve purchase will distribute the buy-now price or refund the buyer if something went wrong
+        .then(ext_self::resolve_auction_purchase(
+            AuctionPurchase {
+                buyer_id,
+                seller_id: auction.owner_id,
+                price: buy_now_price,
+                nft_contract_id,
+                token_id,
+                escrowed: auction.escrowed,
+            },
             env::current_account_id(),
             NO_DEPOSIT,
             GAS_FOR_RESOLVE_PURCHASE,
         ))
     }
 
+    //ends an auction that is over without being sold. Anyone can call this.
+    //if the reserve wasn't met, the top bid is refunded and the auction is removed. If nobody bid, the
+    //auction's fallback is applied: it's relisted once, turned into a fixed price sale or simply removed.
+    //removing the auction releases the storage the seller was paying for it
+    pub fn remove_unsold_auction(&mut self, nft_contract_id: AccountId, token_id: String) {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+
+        let mut auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        assert!(
+            env::block_timestamp() > auction_settles_at(&auction),
+            "The auction is not over yet"
+        );
+        assert!(
+            auction.winner.is_none() || !auction_reserve_met(&auction),
+            "Auction did not end unsold"
+        );
+
+        //settle the sealed bids that weren't revealed before the auction is ended
+        self.internal_release_sealed_bids(&contract_and_auction_token_id);
+
+        let outcome = if let Some(winner_id) = auction.winner.clone() {
+            //the auction ended unsold so the top bidder gets their full bid back
+            self.internal_release_escrow(auction.bid_escrow.0);
+            Promise::new(winner_id).transfer(auction.bid_escrow.0);
+            AuctionOutcome::ReserveNotMet
+        } else {
+            //a fixed price sale can only be listed while the auction approval can still be used
+            let approval_expires_at = auction_approval_expires_at(&auction);
+            match auction.no_bid_fallback.take() {
+                //list the auction again for the same duration starting now. It is only relisted once
+                Some(NoBidFallback::Relist { duration }) => {
+                    let start_time = env::block_timestamp();
+                    let end_time = start_time + duration;
+                    //the hard cap on extensions moves with the end time
+                    let delay = end_time - auction.end_time as u64;
+                    if let Some(extension) = auction.extension.as_mut() {
+                        extension.max_end_time = extension
+                            .max_end_time
+                            .map(|max_end_time| max_end_time + delay);
+                    }
+                    //so does the reveal deadline of sealed-bid auctions
+                    if let Some(sealed_bid) = auction.sealed_bid.as_mut() {
+                        sealed_bid.reveal_end_time += delay;
+                    }
+                    auction.start_time = start_time as u128;
+                    auction.end_time = end_time as u128;
+                    auction.extensions = vec![];
+                    self.auctions
+                        .insert(&contract_and_auction_token_id, &auction);
+
+                    emit_auction_ended(
+                        &nft_contract_id,
+                        &token_id,
+                        AuctionOutcome::Relisted {
+                            start_time,
+                            end_time,
+                        },
+                    );
+                    return;
+                }
+                //list the token for a fixed price in NEAR with the same approval the auction was using.
+                //the sale expires with the auction approval unless the market holds the token
+                Some(NoBidFallback::FixedPrice { sale_conditions })
+                    if auction.escrowed || env::block_timestamp() < approval_expires_at =>
+                {
+                    let auction =
+                        self.internal_remove_auction(nft_contract_id.clone(), token_id.clone());
+                    let mut fixed_sale_conditions = HashMap::new();
+                    fixed_sale_conditions.insert(near_currency(), sale_conditions);
+                    self.internal_add_sale(Sale {
+                        owner_id: auction.owner_id,
+                        approval_id: auction.auction_id,
+                        nft_contract_id: auction.nft_contract_id,
+                        token_id: token_id.clone(),
+                        sale_conditions: fixed_sale_conditions,
+                        escrowed: auction.escrowed,
+                        expires_at: if auction.escrowed {
+                            None
+                        } else {
+                            Some(approval_expires_at)
+                        },
+                        dutch_auction: None,
+                        reserved_for: None,
+                    });
+
+                    emit_auction_ended(
+                        &nft_contract_id,
+                        &token_id,
+                        AuctionOutcome::ListedForSale {
+                            price: sale_conditions.0.to_string(),
+                        },
+                    );
+                    return;
+                }
+                _ => AuctionOutcome::NoBids,
+            }
+        };
+
+        let auction = self.internal_remove_auction(nft_contract_id.clone(), token_id.clone());
+
+        //if the market was holding the token, we give it back to the owner. Otherwise the market
+        //gives up its auction approval so the token is unlocked on the nft contract
+        if auction.escrowed {
+            self.internal_return_escrowed_token(
+                nft_contract_id.clone(),
+                token_id.clone(),
+                auction.owner_id,
+            );
+        } else {
+            internal_revoke_auction_approval(nft_contract_id.clone(), token_id.clone());
+        }
+
+        emit_auction_ended(&nft_contract_id, &token_id, outcome);
+    }
+
+    //removes an auction that the seller cancelled on the nft contract and refunds the current winner.
+    //anyone can call this since the nft contract is the source of truth for the cancellation
+    pub fn remove_cancelled_auction(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Promise {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+
+        let auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        //escrowed auctions are not backed by an auction approval so they can't be cancelled on the nft contract
+        assert!(!auction.escrowed, "Auction is held in escrow by the market");
+        //a claimed NFT also consumes the auction approval so it must not be mistaken for a cancellation
+        assert!(!auction.is_nft_claimed, "NFT is already claimed");
+
+        //ask the nft contract if the auction approval given to the market is still live
+        ext_contract::nft_auction_is_live(
+            token_id.clone(),
+            env::current_account_id(),
+            auction.auction_id,
+            nft_contract_id.clone(),
+            NO_DEPOSIT,
+            GAS_FOR_NFT_AUCTION_CHECK,
+        )
+        .then(ext_self::resolve_cancelled_auction(
+            nft_contract_id,
+            token_id,
+            auction.auction_id,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_CANCELLED_AUCTION,
+        ))
+    }
+
+    //private method used to resolve the nft_auction_is_live check. Returns true if the auction was removed
+    #[private]
+    pub fn resolve_cancelled_auction(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        auction_id: u64,
+    ) -> bool {
+        //only act on a successful answer from the nft contract saying the auction is no longer live
+        let is_live = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(true)
+            }
+            _ => true,
+        };
+        if is_live {
+            env::log_str("Auction is still live on the nft contract");
+            return false;
+        }
+
+        //make sure the auction wasn't relisted with a new auction ID in the meantime
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        match self.auctions.get(&contract_and_auction_token_id) {
+            Some(auction) if auction.auction_id == auction_id && !auction.is_nft_claimed => {}
+            _ => return false,
+        }
+
+        let auction = self.internal_remove_auction(nft_contract_id, token_id);
+
+        //the seller cancelled the auction so the current winner gets their full bid back
+        if let Some(winner_id) = auction.winner {
+            self.internal_release_escrow(auction.bid_escrow.0);
+            Promise::new(winner_id).transfer(auction.bid_escrow.0);
+        }
+
+        true
+    }
+
+    /*
+        private method used to resolve the promise when settling an auction. If the nft transfer went through, the escrowed
+        top bid is distributed to the accounts in the payout. If there's a problem, the bidder gets their escrowed bid back
+        and a token held in custody by the market goes back to the seller.
+    */
     #[private]
-    pub fn resolve_auction_purchase(&mut self, buyer_id: AccountId, price: U128) -> U128 {
+    pub fn resolve_auction_purchase(&mut self, purchase: AuctionPurchase) -> U128 {
+        let AuctionPurchase {
+            buyer_id,
+            seller_id,
+            price,
+            nft_contract_id,
+            token_id,
+            escrowed,
+        } = purchase;
+
+        //the escrowed top bid is either paid out or refunded from here on
+        self.internal_release_escrow(price.0);
+
         // checking for payout information returned from the nft_transfer_payout method
-        let payout_option = promise_result_as_success().and_then(|value| {
-            //if we set the payout_option to None, that means something went wrong and we should refund the buyer
-            near_sdk::serde_json::from_slice::<Payout>(&value)
-                //converts the result to an optional value
-                .ok()
-                //returns None if the none. Otherwise executes the following logic
-                .and_then(|payout_object| {
-                    //we'll check if length of the payout object is > 10 or it's empty. In either case, we return None
-                    if payout_object.payout.len() > 10 || payout_object.payout.is_empty() {
-                        env::log_str("Cannot have more than 10 royalties");
-                        None
-
-                    //if the payout object is the correct length, we move forward
-                    } else {
-                        //we'll keep track of how much the nft contract wants us to payout. Starting at the full price payed by the buyer
-                        let mut remainder = price.0;
-
-                        //loop through the payout and subtract the values from the remainder.
-                        for &value in payout_object.payout.values() {
-                            //checked sub checks for overflow or any errors and returns None if there are problems
-                            remainder = remainder.checked_sub(value.0)?;
-                        }
-                        //Check to see if the NFT contract sent back a faulty payout that requires us to pay more or too little.
-                        //The remainder will be 0 if the payout summed to the total price. The remainder will be 1 if the royalties
-                        //we something like 3333 + 3333 + 3333.
-                        if remainder == 0 || remainder == 1 {
-                            //set the payout_option to be the payout because nothing went wrong
-                            Some(payout_object.payout)
-                        } else {
-                            //if the remainder was anything but 1 or 0, we return None
-                            None
-                        }
-                    }
-                })
-        });
+        let payout_option = verify_payout(price, &seller_id);
 
         // if the payout option was some payout, we set this payout variable equal to that some payout
         let payout = if let Some(payout_option) = payout_option {
             payout_option
-        //if the payout option was None, we refund the buyer for the price they payed and return
+        //if the payout option was None, we refund the bidder their escrowed bid and return
         } else {
             Promise::new(buyer_id).transfer(u128::from(price));
+            //the market still holds escrowed tokens so we give them back to the seller
+            if escrowed {
+                self.internal_return_escrowed_token(nft_contract_id, token_id, seller_id);
+            }
             // leave function and return the price that was refunded
             return price;
         };
 
+        //take the market commission before the royalties are distributed
+        let payout =
+            self.internal_take_market_fee(&nft_contract_id, &near_currency(), price, payout);
+
         // NEAR payouts
         for (receiver_id, amount) in payout {
             Promise::new(receiver_id).transfer(amount.0);
@@ -219,6 +707,177 @@ impl Contract {
     }
 }
 
+//returns when (in nanoseconds) the auction approval the nft contract gave the market expires. The nft contract
+//only knows the end time the auction was created with, before any extension
+pub(crate) fn auction_approval_expires_at(auction: &Auction) -> u64 {
+    let end_time = auction
+        .extensions
+        .first()
+        .map(|extension| extension.previous_end_time)
+        .unwrap_or(auction.end_time as u64);
+    end_time + AUCTION_SETTLEMENT_PERIOD
+}
+
+//validates the buy-now price passed in the listing msg. It has to be above the starting price
+pub(crate) fn auction_buy_now_price(
+    starting_price: SalePriceInYoctoNear,
+    buy_now_price: Option<SalePriceInYoctoNear>,
+) -> Option<SalePriceInYoctoNear> {
+    if let Some(buy_now_price) = buy_now_price {
+        assert!(
+            buy_now_price.0 > starting_price.0,
+            "Buy-now price must be greater than the starting price"
+        );
+    }
+    buy_now_price
+}
+
+impl Contract {
+    //refunds the winner of an auction whatever they bid above the clearing price so that only
+    //the clearing price stays escrowed. Returns the clearing price
+    pub(crate) fn internal_refund_above_clearing_price(&mut self, auction: &mut Auction) -> U128 {
+        let clearing_price = auction_clearing_price(auction);
+        let excess = auction.bid_escrow.0 - clearing_price;
+        if excess > 0 {
+            self.internal_release_escrow(excess);
+            Promise::new(auction.winner.clone().unwrap()).transfer(excess);
+            auction.bid_escrow = U128(clearing_price);
+        }
+        U128(clearing_price)
+    }
+}
+
+//returns the smallest bid (in yoctoNEAR) the auction currently accepts
+pub(crate) fn auction_min_bid(auction: &Auction) -> Balance {
+    let current_price = auction.sale_conditions.0;
+
+    //the first bid has to be greater than the starting price
+    if auction.winner.is_none() {
+        return current_price + 1;
+    }
+
+    current_price + auction_bid_increment(auction, current_price)
+}
+
+//returns how much a bid has to raise the given bid by. A bid always has to raise it by at least 1 yoctoNEAR
+fn auction_bid_increment(auction: &Auction, bid: Balance) -> Balance {
+    let increment = match &auction.min_bid_increment {
+        Some(BidIncrement::Absolute(amount)) => amount.0,
+        Some(BidIncrement::BasisPoints(basis_points)) => {
+            bid * (*basis_points as u128) / (MAX_BASIS_POINTS as u128)
+        }
+        None => 0,
+    };
+    increment.max(1)
+}
+
+//returns the price (in yoctoNEAR) the winner of an auction pays
+pub(crate) fn auction_clearing_price(auction: &Auction) -> Balance {
+    let winning_bid = auction.bid_escrow.0;
+    match (&auction.settlement, &auction.second_bid) {
+        (AuctionSettlement::SecondPrice, Some(second_bid)) => {
+            let mut price = second_bid.0 + auction_bid_increment(auction, second_bid.0);
+            //the winner pays at least the reserve price
+            if let Some(reserve_price) = &auction.reserve_price {
+                price = price.max(reserve_price.0);
+            }
+            price.min(winning_bid)
+        }
+        _ => winning_bid,
+    }
+}
+
+//checks if the current winning bid of an auction meets its reserve price
+pub(crate) fn auction_reserve_met(auction: &Auction) -> bool {
+    match &auction.reserve_price {
+        Some(reserve_price) => {
+            auction.winner.is_some() && auction.sale_conditions.0 >= reserve_price.0
+        }
+        None => true,
+    }
+}
+
+//pushes back the end time of an auction if a bid was placed within its extension window.
+//every extension is recorded on the auction and emitted as an event
+fn extend_auction(auction: &mut Auction) {
+    let extension = if let Some(extension) = &auction.extension {
+        extension
+    } else {
+        return;
+    };
+
+    let now = env::block_timestamp();
+    let previous_end_time = auction.end_time as u64;
+    if previous_end_time - now > extension.window {
+        return;
+    }
+
+    //the end time can't be pushed back past the hard cap (nor past the expiry of the auction approval)
+    let max_end_time = extension
+        .max_end_time
+        .unwrap_or_else(|| auction_approval_expires_at(auction));
+    let end_time = (previous_end_time + extension.extension).min(max_end_time);
+    if end_time <= previous_end_time {
+        return;
+    }
+
+    let bidder_id = auction.winner.clone().unwrap();
+    auction.end_time = end_time as u128;
+    auction.extensions.push(AuctionExtensionRecord {
+        bidder_id: bidder_id.clone(),
+        previous_end_time,
+        end_time,
+    });
+
+    let auction_extended_log: EventLog = EventLog {
+        // Standard name ("unic_market").
+        standard: MARKET_STANDARD_NAME.to_string(),
+        // Version of the events ("1.0.0").
+        version: MARKET_EVENT_VERSION.to_string(),
+        // The data related with the event stored in a vector.
+        event: EventLogVariant::AuctionExtended(vec![AuctionExtendedLog {
+            nft_contract_id: auction.nft_contract_id.clone(),
+            token_id: auction.auction_token.clone(),
+            bidder_id: bidder_id.to_string(),
+            previous_end_time,
+            end_time,
+        }]),
+    };
+
+    // Log the serialized json.
+    env::log_str(&auction_extended_log.to_string());
+}
+
+//emits the event for an auction that ended without being sold
+fn emit_auction_ended(nft_contract_id: &AccountId, token_id: &str, outcome: AuctionOutcome) {
+    let auction_ended_log: EventLog = EventLog {
+        // Standard name ("unic_market").
+        standard: MARKET_STANDARD_NAME.to_string(),
+        // Version of the events ("1.0.0").
+        version: MARKET_EVENT_VERSION.to_string(),
+        // The data related with the event stored in a vector.
+        event: EventLogVariant::AuctionEnded(vec![AuctionEndedLog {
+            nft_contract_id: nft_contract_id.to_string(),
+            token_id: token_id.to_string(),
+            outcome,
+        }]),
+    };
+
+    // Log the serialized json.
+    env::log_str(&auction_ended_log.to_string());
+}
+
+//gives up the auction approval of the market on the nft contract once the auction is removed, which unlocks the token
+fn internal_revoke_auction_approval(nft_contract_id: AccountId, token_id: String) {
+    ext_contract::nft_auction_revoke(
+        token_id,
+        env::current_account_id(),
+        nft_contract_id,
+        1,
+        GAS_FOR_NFT_TRANSFER,
+    );
+}
+
 //this is the cross contract call that we call on our own contract.
 /*
     private method used to resolve the promise when calling nft_transfer_payout. This will take the payout object and
@@ -227,5 +886,12 @@ impl Contract {
 */
 #[ext_contract(ext_self)]
 trait ExtSelf {
-    fn resolve_auction_purchase(&mut self, buyer_id: AccountId, price: U128) -> Promise;
+    fn resolve_auction_purchase(&mut self, purchase: AuctionPurchase) -> U128;
+
+    fn resolve_cancelled_auction(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        auction_id: u64,
+    ) -> bool;
 }
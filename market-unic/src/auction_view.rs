@@ -115,4 +115,18 @@ impl Contract {
         //we're not guaranteed that the unique sale ID passed in will be valid.
         self.auctions.get(&nft_contract_token)
     }
+
+    //returns the smallest bid (in yoctoNEAR) the auction currently accepts
+    pub fn get_auction_min_bid(&self, nft_contract_token: ContractAndTokenId) -> Option<U128> {
+        self.auctions
+            .get(&nft_contract_token)
+            .map(|auction| U128(auction_min_bid(&auction)))
+    }
+
+    //returns whether the current winning bid meets the auction's reserve price without revealing the reserve
+    pub fn get_auction_reserve_met(&self, nft_contract_token: ContractAndTokenId) -> Option<bool> {
+        self.auctions
+            .get(&nft_contract_token)
+            .map(|auction| auction_reserve_met(&auction))
+    }
 }
@@ -0,0 +1,155 @@
+use crate::*;
+
+//struct that records a bid placed on an auction
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bid {
+    //account that placed the bid
+    pub bidder_id: AccountId,
+    //nft contract of the auctioned token
+    pub nft_contract_id: String,
+    //token ID of the auctioned token
+    pub token_id: String,
+    //amount in yoctoNEAR that was bid
+    pub amount: SalePriceInYoctoNear,
+    //time (in nanoseconds) the bid was placed
+    pub placed_at: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// views
+    //returns paginated bids placed on an auction in the order they were placed. (result is a vector of bids)
+    pub fn get_bids_by_auction(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Bid> {
+        //get the bid history of the auction
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let bid_history = if let Some(bid_history) = self.bid_history.get(&contract_and_token_id) {
+            bid_history
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        bid_history
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //since we turned the bids into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //returns paginated bids placed by a bidder on the live auctions. (result is a vector of bids)
+    pub fn get_bids_by_bidder(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Bid> {
+        //get the set of auctions the bidder bid on
+        let auctions_by_bidder =
+            if let Some(auctions_by_bidder) = self.auctions_by_bidder.get(&account_id) {
+                auctions_by_bidder
+            } else {
+                return vec![];
+            };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        auctions_by_bidder
+            .as_vector()
+            .iter()
+            //go through the bid history of every auction and only keep the bids of the bidder
+            .flat_map(|contract_and_token_id| {
+                self.bid_history
+                    .get(&contract_and_token_id)
+                    .unwrap()
+                    .to_vec()
+            })
+            .filter(|bid| bid.bidder_id == account_id)
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //since we turned the bids into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+}
+
+impl Contract {
+    //records a bid in the history of the auction and in the auctions of the bidder
+    pub(crate) fn internal_record_bid(&mut self, bid: Bid) {
+        let contract_and_token_id = format!("{}{}{}", bid.nft_contract_id, DELIMETER, bid.token_id);
+        let bidder_id = bid.bidder_id.clone();
+
+        //get the bid history of the auction. If there is none, we create a new empty vector
+        let mut bid_history = self
+            .bid_history
+            .get(&contract_and_token_id)
+            .unwrap_or_else(|| {
+                Vector::new(
+                    StorageKey::BidHistoryInner {
+                        //we get a new unique prefix for the collection by hashing the unique auction ID
+                        token_hash: hash_contract_and_token_id(&contract_and_token_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+        bid_history.push(&bid);
+        self.bid_history
+            .insert(&contract_and_token_id, &bid_history);
+
+        //get the auctions the bidder bid on. If there are none, we create a new empty set
+        let mut auctions_by_bidder = self.auctions_by_bidder.get(&bidder_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::AuctionsByBidderInner {
+                    //we get a new unique prefix for the collection by hashing the bidder
+                    account_id_hash: hash_account_id(&bidder_id),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        auctions_by_bidder.insert(&contract_and_token_id);
+        self.auctions_by_bidder
+            .insert(&bidder_id, &auctions_by_bidder);
+    }
+
+    //removes the bid history of an auction and the auction from the auctions of every bidder
+    pub(crate) fn internal_remove_bid_history(
+        &mut self,
+        contract_and_token_id: &ContractAndTokenId,
+    ) {
+        let mut bid_history =
+            if let Some(bid_history) = self.bid_history.remove(contract_and_token_id) {
+                bid_history
+            } else {
+                return;
+            };
+
+        for bid in bid_history.iter() {
+            if let Some(mut auctions_by_bidder) = self.auctions_by_bidder.get(&bid.bidder_id) {
+                auctions_by_bidder.remove(contract_and_token_id);
+                if auctions_by_bidder.is_empty() {
+                    self.auctions_by_bidder.remove(&bid.bidder_id);
+                } else {
+                    self.auctions_by_bidder
+                        .insert(&bid.bidder_id, &auctions_by_bidder);
+                }
+            }
+        }
+
+        bid_history.clear();
+    }
+}
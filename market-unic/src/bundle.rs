@@ -0,0 +1,348 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+//struct that holds one of the tokens of a bundle
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BundleToken {
+    //nft contract where the token was minted
+    pub nft_contract_id: String,
+    //actual token ID
+    pub token_id: String,
+    //market contract's approval ID for the token. None until the owner approves the market for the bundle
+    pub approval_id: Option<u64>,
+}
+
+//struct that holds important information about a bundle of tokens sold together for one price
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bundle {
+    //unique ID of the bundle
+    pub bundle_id: u64,
+    //owner of the bundle and all its tokens
+    pub owner_id: AccountId,
+    //tokens in the bundle (possibly from several nft contracts)
+    pub tokens: Vec<BundleToken>,
+    //price in yoctoNEAR for the whole bundle
+    pub price: SalePriceInYoctoNear,
+}
+
+//struct for keeping track of the sale of a bundle while its tokens are moved into the market's custody
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BundleSale {
+    pub owner_id: AccountId,
+    pub buyer_id: AccountId,
+    pub tokens: Vec<BundleToken>,
+    //price in yoctoNEAR for the whole bundle
+    pub price: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    //creates a bundle of tokens sold together for one price in NEAR. The bundle can be bought once every token
+    //was approved for the market with the msg {"add_to_bundle": {"bundle_id": <bundle ID>}}. Returns the bundle ID
+    pub fn create_bundle(&mut self, tokens: Vec<(AccountId, String)>, price: U128) -> U64 {
+        assert!(
+            tokens.len() >= 2 && tokens.len() <= MAX_BUNDLE_SIZE,
+            "A bundle must have between 2 and {} tokens",
+            MAX_BUNDLE_SIZE
+        );
+        //every token gets a share of the price
+        assert!(
+            price.0 >= tokens.len() as u128,
+            "Price must be at least 1 yoctoNEAR per token"
+        );
+
+        //enforce that the owner has enough storage for 1 EXTRA bundle
+        let owner_id = env::predecessor_account_id();
+        self.internal_assert_storage(&owner_id, 1);
+
+        let mut bundle_tokens: Vec<BundleToken> = vec![];
+        for (nft_contract_id, token_id) in tokens {
+            let nft_contract_id = nft_contract_id.to_string();
+            assert!(
+                !bundle_tokens
+                    .iter()
+                    .any(|token| token.nft_contract_id == nft_contract_id
+                        && token.token_id == token_id),
+                "A token can only be in a bundle once"
+            );
+            bundle_tokens.push(BundleToken {
+                nft_contract_id,
+                token_id,
+                approval_id: None,
+            });
+        }
+
+        let bundle_id = self.next_bundle_id;
+        self.next_bundle_id += 1;
+
+        self.internal_add_bundle(Bundle {
+            bundle_id,
+            owner_id,
+            tokens: bundle_tokens,
+            price,
+        });
+
+        U64(bundle_id)
+    }
+
+    //removes a bundle from the market. The market gives up the approvals it was given for the bundle
+    #[payable]
+    pub fn remove_bundle(&mut self, bundle_id: U64) {
+        //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
+        assert_one_yocto();
+
+        let bundle = self.internal_remove_bundle(bundle_id.0);
+        assert_eq!(
+            env::predecessor_account_id(),
+            bundle.owner_id,
+            "Must be bundle owner"
+        );
+
+        for token in bundle.tokens {
+            if token.approval_id.is_some() {
+                ext_contract::nft_revoke(
+                    token.token_id,
+                    env::current_account_id(),
+                    token.nft_contract_id.parse().unwrap(),
+                    1,
+                    GAS_FOR_NFT_TRANSFER,
+                );
+            }
+        }
+    }
+
+    //buys every token of a bundle. Every token is transferred into the market's custody with nft_transfer_payout
+    //for its pro-rata share of the price. Only once all of them arrived with a valid payout, they are sent to the
+    //buyer and the price is paid out. Otherwise the tokens that did move go back to the owner and the buyer is refunded
+    #[payable]
+    pub fn buy_bundle(&mut self, bundle_id: U64) -> Promise {
+        let bundle = self.bundles.get(&bundle_id.0).expect("No bundle");
+
+        let buyer_id = env::predecessor_account_id();
+        assert_ne!(bundle.owner_id, buyer_id, "Cannot buy your own bundle.");
+        assert!(
+            bundle
+                .tokens
+                .iter()
+                .all(|token| token.approval_id.is_some()),
+            "Every token of the bundle must be approved first"
+        );
+
+        //make sure the deposit covers the price and refund the excess
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= bundle.price.0,
+            "Attached deposit must be greater than or equal to the current price: {:?}",
+            bundle.price
+        );
+        if deposit > bundle.price.0 {
+            Promise::new(buyer_id.clone()).transfer(deposit - bundle.price.0);
+        }
+        //the price is escrowed until it's paid out or refunded
+        self.internal_lock_escrow(bundle.price.0);
+
+        //get the bundle object by removing the bundle. This makes sure it can only be bought once
+        let bundle = self.internal_remove_bundle(bundle_id.0);
+
+        //move every token into the market's custody at once, getting the payout for its share of the price
+        let shares = bundle_price_shares(bundle.price, bundle.tokens.len());
+        let mut custody: Option<Promise> = None;
+        for (token, share) in bundle.tokens.iter().zip(shares) {
+            let transfer = ext_contract::nft_transfer_payout(
+                env::current_account_id(),
+                token.token_id.clone(),
+                token.approval_id.unwrap(),
+                "bundle held by market".to_string(),
+                share,
+                10,
+                token.nft_contract_id.parse().unwrap(),
+                1,
+                GAS_FOR_NFT_TRANSFER,
+            );
+            custody = Some(match custody {
+                Some(custody) => custody.and(transfer),
+                None => transfer,
+            });
+        }
+
+        custody.unwrap().then(ext_self::resolve_bundle_payout(
+            BundleSale {
+                owner_id: bundle.owner_id,
+                buyer_id,
+                tokens: bundle.tokens,
+                price: bundle.price,
+            },
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_BUNDLE_PAYOUT,
+        ))
+    }
+
+    //private method used to resolve moving the tokens of a bundle into the market's custody. The bundle is sold as
+    //a whole: if every token arrived with a valid payout, the tokens are sent to the buyer and the price is paid out.
+    //otherwise the tokens that did move go back to the owner and the buyer is refunded the full price.
+    //returns the price paid out or refunded
+    #[private]
+    pub fn resolve_bundle_payout(&mut self, sale: BundleSale) -> U128 {
+        let BundleSale {
+            owner_id,
+            buyer_id,
+            tokens,
+            price,
+        } = sale;
+
+        //the price of the bundle is either paid out or refunded from here on
+        self.internal_release_escrow(price.0);
+
+        //check which tokens were moved into the market's custody and the payout returned for each of them
+        let shares = bundle_price_shares(price, tokens.len());
+        let mut moved: Vec<bool> = vec![];
+        let mut payouts: Vec<Option<HashMap<AccountId, U128>>> = vec![];
+        for (index, share) in shares.into_iter().enumerate() {
+            match env::promise_result(index as u64) {
+                PromiseResult::Successful(value) => {
+                    moved.push(true);
+                    payouts.push(verify_payout_value(&value, share, &owner_id));
+                }
+                _ => {
+                    moved.push(false);
+                    payouts.push(None);
+                }
+            }
+        }
+
+        if payouts.iter().any(|payout| payout.is_none()) {
+            env::log_str("Not every token of the bundle could be sold, rolling back");
+            for (token, moved) in tokens.into_iter().zip(moved) {
+                if moved {
+                    self.internal_return_escrowed_token(
+                        token.nft_contract_id.parse().unwrap(),
+                        token.token_id,
+                        owner_id.clone(),
+                    );
+                }
+            }
+            Promise::new(buyer_id).transfer(price.0);
+            // leave function and return the price that was refunded
+            return price;
+        }
+
+        for (token, payout) in tokens.into_iter().zip(payouts) {
+            let nft_contract_id: AccountId = token.nft_contract_id.parse().unwrap();
+
+            //the market owns the token so no approval is needed
+            self.internal_send_escrowed_token(
+                nft_contract_id.clone(),
+                token.token_id,
+                buyer_id.clone(),
+                "bundle payout from market",
+            );
+
+            //take the market commission before the royalties are distributed
+            let payout = payout.unwrap();
+            let token_price = U128(payout.values().map(|amount| amount.0).sum());
+            let payout = self.internal_take_market_fee(
+                &nft_contract_id,
+                &near_currency(),
+                token_price,
+                payout,
+            );
+
+            // NEAR payouts
+            for (receiver_id, amount) in payout {
+                Promise::new(receiver_id).transfer(amount.0);
+            }
+        }
+
+        //return the price payout out
+        price
+    }
+}
+
+//splits the price of a bundle into one share per token. The shares are even and the rounding remainder
+//is spread (1 yoctoNEAR each) over the first tokens so the shares add up to the price
+pub(crate) fn bundle_price_shares(price: U128, token_count: usize) -> Vec<U128> {
+    let token_count = token_count as u128;
+    let share = price.0 / token_count;
+    let remainder = price.0 % token_count;
+    (0..token_count)
+        .map(|index| U128(share + if index < remainder { 1 } else { 0 }))
+        .collect()
+}
+
+impl Contract {
+    //internal method used when the owner approves one of the tokens of a bundle for the market
+    pub(crate) fn internal_approve_bundle_token(
+        &mut self,
+        bundle_id: u64,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+    ) {
+        let mut bundle = self.bundles.get(&bundle_id).expect("No bundle");
+        assert_eq!(owner_id, bundle.owner_id, "Must be bundle owner");
+
+        let token = bundle
+            .tokens
+            .iter_mut()
+            .find(|token| {
+                token.nft_contract_id == nft_contract_id.to_string() && token.token_id == token_id
+            })
+            .expect("Token is not in the bundle");
+        token.approval_id = Some(approval_id);
+
+        self.bundles.insert(&bundle_id, &bundle);
+    }
+
+    //internal method for adding a bundle to the market and the index for its owner
+    pub(crate) fn internal_add_bundle(&mut self, bundle: Bundle) {
+        let owner_id = bundle.owner_id.clone();
+        self.bundles.insert(&bundle.bundle_id, &bundle);
+
+        //get the bundles for the given owner. If there are none, we create a new empty set
+        let mut bundles_by_owner_id =
+            self.bundles_by_owner_id.get(&owner_id).unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::BundlesByOwnerIdInner {
+                        //we get a new unique prefix for the collection by hashing the owner
+                        account_id_hash: hash_account_id(&owner_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+        bundles_by_owner_id.insert(&bundle.bundle_id);
+        self.bundles_by_owner_id
+            .insert(&owner_id, &bundles_by_owner_id);
+    }
+
+    //internal method for removing a bundle from the market. This returns the previously removed bundle object
+    pub(crate) fn internal_remove_bundle(&mut self, bundle_id: u64) -> Bundle {
+        let bundle = self.bundles.remove(&bundle_id).expect("No bundle");
+
+        //remove the bundle ID from the set of bundles for the owner
+        let mut bundles_by_owner_id = self
+            .bundles_by_owner_id
+            .get(&bundle.owner_id)
+            .expect("No bundle by owner_id");
+        bundles_by_owner_id.remove(&bundle_id);
+        if bundles_by_owner_id.is_empty() {
+            self.bundles_by_owner_id.remove(&bundle.owner_id);
+        } else {
+            self.bundles_by_owner_id
+                .insert(&bundle.owner_id, &bundles_by_owner_id);
+        }
+
+        bundle
+    }
+}
+
+//this is the cross contract call that we call on our own contract.
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn resolve_bundle_payout(&mut self, sale: BundleSale) -> U128;
+}
@@ -0,0 +1,48 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// views
+    //returns the number of bundles the marketplace has up (as a string)
+    pub fn get_supply_bundles(&self) -> U64 {
+        //returns the bundles object length wrapped as a U64
+        U64(self.bundles.len())
+    }
+
+    //returns paginated bundle objects for a given account. (result is a vector of bundles)
+    pub fn get_bundles_by_owner_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Bundle> {
+        //get the set of bundle IDs for the given owner
+        let bundles_by_owner_id =
+            if let Some(bundles_by_owner_id) = self.bundles_by_owner_id.get(&account_id) {
+                bundles_by_owner_id
+            } else {
+                return vec![];
+            };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the bundle IDs
+        bundles_by_owner_id
+            .as_vector()
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //we'll map the bundle IDs into Bundle objects
+            .map(|bundle_id| self.bundles.get(&bundle_id).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //get a bundle by its ID
+    pub fn get_bundle(&self, bundle_id: U64) -> Option<Bundle> {
+        self.bundles.get(&bundle_id.0)
+    }
+}
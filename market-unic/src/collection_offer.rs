@@ -0,0 +1,258 @@
+use crate::*;
+
+//struct that holds important information about each offer made on any token of a collection
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CollectionOffer {
+    //unique ID of the collection offer
+    pub offer_id: u64,
+    //account that made the offer and will receive the tokens
+    pub buyer_id: AccountId,
+    //nft contract whose tokens the offer can be filled with
+    pub nft_contract_id: String,
+    //price in yoctoNEAR paid for every token
+    pub price: SalePriceInYoctoNear,
+    //how many tokens can still be sold to this offer. The market escrows price * quantity
+    pub quantity: u32,
+    //time (in nanoseconds) after which the offer can no longer be filled
+    pub expires_at: Option<u64>,
+}
+
+//struct for keeping track of a collection offer fill while the nft contract transfers the token
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CollectionOfferFill {
+    //ID of the collection offer that is filled
+    pub offer_id: U64,
+    //nft contract where the token was minted
+    pub nft_contract_id: AccountId,
+    pub buyer_id: AccountId,
+    pub seller_id: AccountId,
+    //price in yoctoNEAR paid for the token
+    pub price: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    //make an offer on any token of a collection. The attached deposit must cover the price for every token
+    //and is held by the market until the offer is filled, cancelled or expires. Returns the ID of the offer.
+    #[payable]
+    pub fn make_collection_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        price: U128,
+        quantity: u32,
+        expires_at: Option<u64>,
+    ) -> U64 {
+        assert!(price.0 > 0, "Price must be greater than 0");
+        assert!(quantity > 0, "Quantity must be greater than 0");
+
+        //make sure the deposit covers the price of every token the offer can be filled with
+        let deposit = env::attached_deposit();
+        let escrow = price.0 * quantity as u128;
+        assert!(
+            deposit >= escrow,
+            "Attached deposit must be greater than or equal to price * quantity: {:?}",
+            escrow
+        );
+
+        //the expiry is passed in seconds and stored in nanoseconds
+        let expires_at = expires_at.map(|expires_at| expires_at * 1_000_000_000);
+        if let Some(expires_at) = expires_at {
+            assert!(
+                expires_at > env::block_timestamp(),
+                "Offer must expire in the future"
+            );
+        }
+
+        //enforce that the buyer has enough storage for 1 EXTRA offer
+        let buyer_id = env::predecessor_account_id();
+        self.internal_assert_storage(&buyer_id, 1);
+
+        //refund whatever was attached on top of the escrow
+        if deposit > escrow {
+            Promise::new(buyer_id.clone()).transfer(deposit - escrow);
+        }
+
+        self.internal_lock_escrow(escrow);
+
+        let offer_id = self.next_collection_offer_id;
+        self.next_collection_offer_id += 1;
+
+        self.internal_add_collection_offer(CollectionOffer {
+            offer_id,
+            buyer_id,
+            nft_contract_id: nft_contract_id.to_string(),
+            price,
+            quantity,
+            expires_at,
+        });
+
+        U64(offer_id)
+    }
+
+    //cancels a collection offer and refunds the escrow for the tokens that weren't sold to the buyer
+    #[payable]
+    pub fn cancel_collection_offer(&mut self, offer_id: U64) {
+        //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
+        assert_one_yocto();
+
+        let offer = self
+            .collection_offers
+            .get(&offer_id.0)
+            .expect("No collection offer");
+        assert_eq!(
+            offer.buyer_id,
+            env::predecessor_account_id(),
+            "Only the buyer can cancel the offer"
+        );
+
+        let offer = self.internal_remove_collection_offer(offer_id.0);
+        let unfilled_escrow = offer.price.0 * offer.quantity as u128;
+        self.internal_release_escrow(unfilled_escrow);
+        Promise::new(offer.buyer_id).transfer(unfilled_escrow);
+    }
+
+    //removes an expired collection offer and refunds the unfilled escrow to the buyer. Anyone can call this.
+    pub fn remove_expired_collection_offer(&mut self, offer_id: U64) {
+        let offer = self
+            .collection_offers
+            .get(&offer_id.0)
+            .expect("No collection offer");
+        assert!(
+            collection_offer_is_expired(&offer),
+            "Offer has not expired yet"
+        );
+
+        let offer = self.internal_remove_collection_offer(offer_id.0);
+        let unfilled_escrow = offer.price.0 * offer.quantity as u128;
+        self.internal_release_escrow(unfilled_escrow);
+        Promise::new(offer.buyer_id).transfer(unfilled_escrow);
+    }
+
+    /*
+        private method used to resolve the promise when calling nft_transfer_payout for a collection offer fill.
+        If everything is fine, it will pay the accounts. If there's a problem, the token is given back to the offer
+        (or refunded to the buyer if the offer was removed in the meantime).
+    */
+    #[private]
+    pub fn resolve_collection_offer_fill(&mut self, fill: CollectionOfferFill) -> U128 {
+        let CollectionOfferFill {
+            offer_id,
+            nft_contract_id,
+            buyer_id,
+            seller_id,
+            price,
+        } = fill;
+
+        // checking for payout information returned from the nft_transfer_payout method
+        let payout = if let Some(payout) = verify_payout(price, &seller_id) {
+            payout
+        } else {
+            //if the offer is still live we put the unit back (its price is still escrowed),
+            //otherwise its escrow would be lost so we refund it
+            if let Some(mut offer) = self.collection_offers.get(&offer_id.0) {
+                offer.quantity += 1;
+                self.collection_offers.insert(&offer_id.0, &offer);
+            } else {
+                self.internal_release_escrow(price.0);
+                Promise::new(buyer_id).transfer(price.0);
+            }
+            // leave function and return the price that was given back
+            return price;
+        };
+
+        //the escrowed price is paid out
+        self.internal_release_escrow(price.0);
+
+        //take the market commission before the royalties are distributed
+        let payout =
+            self.internal_take_market_fee(&nft_contract_id, &near_currency(), price, payout);
+
+        // NEAR payouts
+        for (receiver_id, amount) in payout {
+            Promise::new(receiver_id).transfer(amount.0);
+        }
+
+        //return the price payout out
+        price
+    }
+}
+
+impl Contract {
+    //internal method used when a token owner sells to a collection offer by approving the market.
+    //this will take one token off the offer, transfer and get the payout from the nft contract, and then distribute royalties
+    pub(crate) fn internal_fill_collection_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+        offer_id: u64,
+    ) -> Promise {
+        let mut offer = self
+            .collection_offers
+            .get(&offer_id)
+            .expect("No collection offer");
+
+        assert_eq!(
+            offer.nft_contract_id,
+            nft_contract_id.to_string(),
+            "Offer is for a different collection"
+        );
+        assert!(!collection_offer_is_expired(&offer), "Offer has expired");
+        assert_ne!(owner_id, offer.buyer_id, "Cannot fill your own offer.");
+
+        //take one token off the offer. Once every token is bought, the offer is removed.
+        //the escrowed price stays locked until resolve_collection_offer_fill either pays it out or gives it back to the offer
+        offer.quantity -= 1;
+        if offer.quantity == 0 {
+            self.internal_remove_collection_offer(offer_id);
+        } else {
+            self.collection_offers.insert(&offer_id, &offer);
+        }
+        //a listing of the token would be left behind with a consumed approval so it's taken down
+        self.internal_remove_stale_sale(&nft_contract_id, &token_id);
+
+        //initiate a cross contract call to the nft contract. This will transfer the token to the buyer and return
+        //a payout object used for the market to distribute the escrowed price to the appropriate accounts.
+        ext_contract::nft_transfer_payout(
+            offer.buyer_id.clone(),
+            token_id,
+            approval_id,
+            "payout from market".to_string(),
+            offer.price,
+            10,
+            nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        //resolve the fill which will pay the accounts or give the unit back to the offer if something went wrong
+        .then(ext_self::resolve_collection_offer_fill(
+            CollectionOfferFill {
+                offer_id: U64(offer_id),
+                nft_contract_id,
+                buyer_id: offer.buyer_id,
+                seller_id: owner_id,
+                price: offer.price,
+            },
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_PURCHASE,
+        ))
+    }
+}
+
+//checks if a collection offer can no longer be filled
+pub(crate) fn collection_offer_is_expired(offer: &CollectionOffer) -> bool {
+    offer
+        .expires_at
+        .map(|expires_at| env::block_timestamp() >= expires_at)
+        .unwrap_or(false)
+}
+
+//this is the cross contract call that we call on our own contract.
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn resolve_collection_offer_fill(&mut self, fill: CollectionOfferFill) -> U128;
+}
@@ -0,0 +1,82 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// views
+    //returns the number of collection offers the marketplace holds (as a string)
+    pub fn get_supply_collection_offers(&self) -> U64 {
+        //returns the collection offers object length wrapped as a U64
+        U64(self.collection_offers.len())
+    }
+
+    //returns paginated collection offer objects made on a given nft contract. (result is a vector of offers)
+    pub fn get_collection_offers_by_nft_contract_id(
+        &self,
+        nft_contract_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<CollectionOffer> {
+        //get the set of collection offer IDs for the given nft contract
+        let offers_by_nft_contract_id = if let Some(offers_by_nft_contract_id) = self
+            .collection_offers_by_nft_contract_id
+            .get(&nft_contract_id)
+        {
+            offers_by_nft_contract_id
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the offer IDs
+        offers_by_nft_contract_id
+            .as_vector()
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //we'll map the offer IDs into CollectionOffer objects
+            .map(|offer_id| self.collection_offers.get(&offer_id).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //returns paginated collection offer objects made by a given buyer. (result is a vector of offers)
+    pub fn get_collection_offers_by_buyer_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<CollectionOffer> {
+        //get the set of collection offer IDs for the given buyer
+        let offers_by_buyer_id =
+            if let Some(offers_by_buyer_id) = self.collection_offers_by_buyer_id.get(&account_id) {
+                offers_by_buyer_id
+            } else {
+                return vec![];
+            };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the offer IDs
+        offers_by_buyer_id
+            .as_vector()
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //we'll map the offer IDs into CollectionOffer objects
+            .map(|offer_id| self.collection_offers.get(&offer_id).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //get a collection offer by its ID
+    pub fn get_collection_offer(&self, offer_id: U64) -> Option<CollectionOffer> {
+        self.collection_offers.get(&offer_id.0)
+    }
+}
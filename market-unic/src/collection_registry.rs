@@ -0,0 +1,161 @@
+use crate::*;
+
+//the kinds of listings a collection can be listed with on the market
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ListingType {
+    //fixed price sales, including accepting offers and filling collection offers
+    Sale,
+    //sales whose price decreases over time
+    DutchAuction,
+    //english or sealed-bid auctions
+    Auction,
+    //all-or-nothing bundles
+    Bundle,
+    //token for token swaps
+    Swap,
+}
+
+//settings of an NFT contract registered with the market
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CollectionSettings {
+    //whether the market vouches for the collection
+    pub verified: bool,
+    //commission in basis points taken from the collection's sales instead of the market fee
+    pub market_fee: Option<u32>,
+    //listing types the collection can be listed with. If not passed in, every listing type is allowed
+    pub allowed_listing_types: Option<Vec<ListingType>>,
+}
+
+//struct for returning a registered collection with its settings
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonCollection {
+    pub nft_contract_id: AccountId,
+    #[serde(flatten)]
+    pub settings: CollectionSettings,
+}
+
+#[near_bindgen]
+impl Contract {
+    //allows the owner to register an NFT contract with the market (or update its settings if it's already registered)
+    #[payable]
+    pub fn register_collection(
+        &mut self,
+        nft_contract_id: AccountId,
+        settings: CollectionSettings,
+    ) {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        if let Some(market_fee) = settings.market_fee {
+            crate::fees::assert_market_fee(market_fee);
+        }
+        self.collections.insert(&nft_contract_id, &settings);
+    }
+
+    //allows the owner to remove an NFT contract from the market. Existing listings are left as they are
+    //but no new listings can be created for the collection (once the registry is enforced)
+    #[payable]
+    pub fn unregister_collection(&mut self, nft_contract_id: AccountId) {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        self.collections
+            .remove(&nft_contract_id)
+            .expect("Collection is not registered");
+    }
+
+    //allows the owner to turn the registry on or off. It's on by default. While it's off (e.g. while the existing
+    //collections are being registered) unregistered NFT contracts can still be listed.
+    //registered collections always keep their listing type restrictions
+    #[payable]
+    pub fn set_registry_enforced(&mut self, enforced: bool) {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        self.registry_enforced = enforced;
+    }
+
+    /// views
+    //returns whether only registered NFT contracts can be listed on the market
+    pub fn get_registry_enforced(&self) -> bool {
+        self.registry_enforced
+    }
+
+    //returns the number of collections registered with the market (as a string)
+    pub fn get_supply_collections(&self) -> U64 {
+        U64(self.collections.len())
+    }
+
+    //returns paginated collections registered with the market. (result is a vector of collections)
+    pub fn get_collections(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<JsonCollection> {
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.collections
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            .map(|(nft_contract_id, settings)| JsonCollection {
+                nft_contract_id,
+                settings,
+            })
+            .collect()
+    }
+
+    //get the settings of a registered collection
+    pub fn get_collection(&self, nft_contract_id: AccountId) -> Option<JsonCollection> {
+        self.collections
+            .get(&nft_contract_id)
+            .map(|settings| JsonCollection {
+                nft_contract_id,
+                settings,
+            })
+    }
+}
+
+impl Contract {
+    //make sure an NFT contract is registered with the market and can be listed with the given listing type
+    pub(crate) fn assert_collection_allows(
+        &self,
+        nft_contract_id: &AccountId,
+        listing_type: ListingType,
+    ) {
+        let settings = match self.collections.get(nft_contract_id) {
+            Some(settings) => settings,
+            //until the registry is enforced, unregistered NFT contracts can be listed with any listing type
+            None if !self.registry_enforced => return,
+            None => env::panic_str(&format!(
+                "NFT contract {} is not registered with the market",
+                nft_contract_id
+            )),
+        };
+
+        if let Some(allowed_listing_types) = settings.allowed_listing_types {
+            assert!(
+                allowed_listing_types.contains(&listing_type),
+                "Listing type is not allowed for NFT contract {}",
+                nft_contract_id
+            );
+        }
+    }
+
+    //returns the commission (in basis points) taken from a collection's sales
+    pub(crate) fn internal_collection_market_fee(&self, nft_contract_id: &AccountId) -> u32 {
+        self.collections
+            .get(nft_contract_id)
+            .and_then(|settings| settings.market_fee)
+            .unwrap_or(self.market_fee)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::*;
+
+//struct for the dutch auction parameters passed in the listing msg (times are in seconds)
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuctionArgs {
+    pub start_price: SalePriceInYoctoNear,
+    pub end_price: SalePriceInYoctoNear,
+    pub start_time: u64,
+    pub duration: u64,
+}
+
+//struct that holds the configuration of a sale whose price drops over time
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuction {
+    //price in yoctoNEAR when the auction starts
+    pub start_price: SalePriceInYoctoNear,
+    //price in yoctoNEAR the auction drops to once the duration is over
+    pub end_price: SalePriceInYoctoNear,
+    //time (in nanoseconds) when the price starts dropping
+    pub start_time: u64,
+    //how long (in nanoseconds) it takes for the price to drop from the start to the end price
+    pub duration: u64,
+}
+
+impl DutchAuctionArgs {
+    //validates the passed in parameters and converts the times to nanoseconds
+    pub fn into_dutch_auction(self) -> DutchAuction {
+        assert!(
+            self.start_price.0 > self.end_price.0,
+            "Start price must be greater than the end price"
+        );
+        assert!(self.duration > 0, "Duration must be greater than 0");
+
+        DutchAuction {
+            start_price: self.start_price,
+            end_price: self.end_price,
+            start_time: self.start_time * 1_000_000_000,
+            duration: self.duration * 1_000_000_000,
+        }
+    }
+}
+
+impl DutchAuction {
+    //calculates the price at the current block timestamp. The price drops linearly from the start
+    //price to the end price over the duration and stays at the end price afterwards
+    pub fn current_price(&self) -> Balance {
+        let now = env::block_timestamp();
+        if now <= self.start_time {
+            return self.start_price.0;
+        }
+
+        let elapsed = now - self.start_time;
+        if elapsed >= self.duration {
+            return self.end_price.0;
+        }
+
+        let price_drop = self.start_price.0 - self.end_price.0;
+        self.start_price.0 - price_drop * (elapsed as u128) / (self.duration as u128)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// views
+    //returns the current price in yoctoNEAR of a sale. For dutch auctions, this is computed from the block timestamp
+    pub fn get_sale_price(&self, nft_contract_id: AccountId, token_id: String) -> Option<U128> {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        self.sales
+            .get(&contract_and_token_id)
+            .and_then(|sale| sale_price_in_near(&sale))
+            .map(U128)
+    }
+}
+
+//returns the current price of a sale in yoctoNEAR (if it's priced in NEAR)
+pub(crate) fn sale_price_in_near(sale: &Sale) -> Option<Balance> {
+    if let Some(dutch_auction) = &sale.dutch_auction {
+        return Some(dutch_auction.current_price());
+    }
+
+    sale.sale_conditions
+        .get(&near_currency())
+        .map(|price| price.0)
+}
@@ -0,0 +1,132 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+//struct for returning how the NEAR held by the market is accounted for
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowReport {
+    //NEAR held for live auction bids, offers and collection offers
+    pub total_escrow: U128,
+    //NEAR commission collected by the market that wasn't withdrawn yet
+    pub treasury: U128,
+    //balance of the market contract
+    pub account_balance: U128,
+    //part of the balance that is locked to pay for the contract's storage
+    pub storage_cost: U128,
+    //whether the balance (minus the storage cost) covers all the live escrow and the treasury
+    pub is_covered: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    //gives an escrowed token that couldn't be returned before back to its owner
+    #[payable]
+    pub fn claim_escrowed_token(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Promise {
+        //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
+        assert_one_yocto();
+
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let owner_id = self
+            .claimable_tokens
+            .remove(&contract_and_token_id)
+            .expect("No claimable token");
+        assert_eq!(
+            env::predecessor_account_id(),
+            owner_id,
+            "Must be token owner"
+        );
+
+        //if the transfer fails again, the token is recorded as claimable again
+        self.internal_return_escrowed_token(nft_contract_id, token_id, owner_id)
+    }
+
+    //private method used to resolve sending an escrowed token to its owner (or buyer). If the transfer failed,
+    //the token stays in the market and is recorded as claimable by that account. Returns true if the token was sent
+    #[private]
+    pub fn resolve_escrowed_token_return(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        owner_id: AccountId,
+    ) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+
+        self.internal_record_claimable_token(nft_contract_id, token_id, owner_id);
+        false
+    }
+
+    /// views
+    //returns the escrow accounting of the market and checks that the contract's balance covers it
+    pub fn get_escrow_report(&self) -> EscrowReport {
+        let treasury = self.treasury.get(&near_currency()).unwrap_or(0);
+        let account_balance = env::account_balance();
+        let storage_cost = env::storage_usage() as u128 * env::storage_byte_cost();
+
+        EscrowReport {
+            total_escrow: U128(self.total_escrow),
+            treasury: U128(treasury),
+            account_balance: U128(account_balance),
+            storage_cost: U128(storage_cost),
+            is_covered: account_balance.saturating_sub(storage_cost)
+                >= self.total_escrow + treasury,
+        }
+    }
+
+    //returns the account that can claim an escrowed token that couldn't be given back (if any)
+    pub fn get_claimable_token_owner(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+    ) -> Option<AccountId> {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        self.claimable_tokens.get(&contract_and_token_id)
+    }
+}
+
+impl Contract {
+    //records NEAR the market starts holding on behalf of a bidder or buyer
+    pub(crate) fn internal_lock_escrow(&mut self, amount: Balance) {
+        self.total_escrow += amount;
+    }
+
+    //records NEAR the market stops holding because it was refunded or paid out
+    pub(crate) fn internal_release_escrow(&mut self, amount: Balance) {
+        self.total_escrow = self
+            .total_escrow
+            .checked_sub(amount)
+            .expect("Released more escrow than the market holds");
+    }
+
+    //records a token the market holds in custody as claimable by the given account through claim_escrowed_token
+    pub(crate) fn internal_record_claimable_token(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+    ) {
+        env::log_str(&format!(
+            "Could not send token {} on {} to {}, it can be claimed through claim_escrowed_token",
+            token_id, nft_contract_id, owner_id
+        ));
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        self.claimable_tokens
+            .insert(&contract_and_token_id, &owner_id);
+    }
+}
+
+//this is the cross contract call that we call on our own contract.
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn resolve_escrowed_token_return(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        owner_id: AccountId,
+    ) -> bool;
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Enum that represents the data type of the EventLog.
+/// The enum can either be an AuctionExtended or an AuctionEnded.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+#[serde(crate = "near_sdk::serde")]
+#[non_exhaustive]
+pub enum EventLogVariant {
+    AuctionExtended(Vec<AuctionExtendedLog>),
+    AuctionEnded(Vec<AuctionEndedLog>),
+}
+
+/// Interface to capture data about an event
+///
+/// Arguments:
+/// * `standard`: name of standard e.g. unic_market
+/// * `version`: e.g. 1.0.0
+/// * `event`: associate event data
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EventLog {
+    pub standard: String,
+    pub version: String,
+
+    // `flatten` to not have "event": {<EventLogVariant>} in the JSON, just have the contents of {<EventLogVariant>}.
+    #[serde(flatten)]
+    pub event: EventLogVariant,
+}
+
+impl fmt::Display for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "EVENT_JSON:{}",
+            &near_sdk::serde_json::to_string(self).map_err(|_| fmt::Error)?
+        ))
+    }
+}
+
+/// An event log to capture an auction being extended by a late bid
+///
+/// Arguments
+/// * `nft_contract_id`: "nft.near"
+/// * `token_id`: "1"
+/// * `bidder_id`: "bidder.near"
+/// * `previous_end_time`: end time (in nanoseconds) before the bid
+/// * `end_time`: new end time (in nanoseconds)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionExtendedLog {
+    pub nft_contract_id: String,
+    pub token_id: String,
+    pub bidder_id: String,
+    pub previous_end_time: u64,
+    pub end_time: u64,
+}
+
+/// An event log to capture an auction ending without being sold
+///
+/// Arguments
+/// * `nft_contract_id`: "nft.near"
+/// * `token_id`: "1"
+/// * `outcome`: why the auction ended unsold and what happened to the token
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionEndedLog {
+    pub nft_contract_id: String,
+    pub token_id: String,
+    pub outcome: AuctionOutcome,
+}
+
+/// The outcome of an auction that ended without being sold
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+#[serde(crate = "near_sdk::serde")]
+pub enum AuctionOutcome {
+    //the top bid didn't meet the reserve price and was refunded
+    ReserveNotMet,
+    //nobody bid and the auction was removed
+    NoBids,
+    //nobody bid and the auction was listed again (times are in nanoseconds)
+    Relisted { start_time: u64, end_time: u64 },
+    //nobody bid and the token was listed as a fixed price sale in yoctoNEAR
+    ListedForSale { price: String },
+}
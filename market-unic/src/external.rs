@@ -20,4 +20,28 @@ trait ExtContract {
         //the maximum amount of accounts the market can payout at once (this is limited by GAS)
 		max_len_payout: u32,
     );
+
+    //transfer a token held in escrow by the market back to its owner
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    );
+
+    //give up the approval of the market on a token
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId);
+
+    //check if the auction approved through approve_nft_auction is still live on the nft contract
+    fn nft_auction_is_live(&self, token_id: TokenId, account_id: AccountId, auction_id: u64);
+
+    //give up the auction approval of the market so the token is unlocked on the nft contract
+    fn nft_auction_revoke(&mut self, token_id: TokenId, account_id: AccountId);
+}
+
+//cross contract call to a fungible token contract to pay out the proceeds of a sale paid in fungible tokens
+#[ext_contract(ext_ft_contract)]
+trait ExtFtContract {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
 }
\ No newline at end of file
@@ -0,0 +1,162 @@
+use crate::*;
+
+//struct for returning the commission configuration of the market
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MarketFeeConfig {
+    //commission in basis points taken from every sale and auction
+    pub market_fee: u32,
+    //accounts the treasury is split between (account -> share in basis points)
+    pub fee_recipients: HashMap<AccountId, u32>,
+}
+
+#[near_bindgen]
+impl Contract {
+    //allows the owner to set the commission (in basis points) taken from every sale and auction
+    #[payable]
+    pub fn set_market_fee(&mut self, market_fee: u32) {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        assert_market_fee(market_fee);
+        self.market_fee = market_fee;
+    }
+
+    //allows the owner to set the accounts the treasury is split between. The shares must add up to 100%
+    #[payable]
+    pub fn set_fee_recipients(&mut self, fee_recipients: HashMap<AccountId, u32>) {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        //the treasury is paid out with one transfer per recipient so we limit how many there can be (this is limited by GAS)
+        assert!(
+            !fee_recipients.is_empty() && fee_recipients.len() <= 10,
+            "Must have between 1 and 10 fee recipients"
+        );
+        let total_shares: u32 = fee_recipients.values().sum();
+        assert_eq!(
+            total_shares, MAX_BASIS_POINTS,
+            "Fee recipient shares must add up to {} basis points",
+            MAX_BASIS_POINTS
+        );
+
+        self.fee_recipients = fee_recipients;
+    }
+
+    //allows the owner to pay out the commission collected in a currency (NEAR if no FT contract ID is passed in)
+    //to the fee recipients. Returns the amount that was paid out.
+    #[payable]
+    pub fn withdraw_treasury(&mut self, ft_token_id: Option<FungibleTokenId>) -> U128 {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        let currency = currency_for(ft_token_id.clone());
+        let balance = self.treasury.remove(&currency).unwrap_or(0);
+
+        //split the balance between the fee recipients according to their share
+        let mut paid_out = 0;
+        for (receiver_id, share) in self.fee_recipients.iter() {
+            let amount = balance * (*share as u128) / (MAX_BASIS_POINTS as u128);
+            if amount == 0 {
+                continue;
+            }
+            paid_out += amount;
+
+            if let Some(ft_token_id) = &ft_token_id {
+                //a failed transfer can be withdrawn later by the fee recipient through withdraw_ft_balance
+                crate::ft_callbacks::internal_ft_payout(
+                    ft_token_id.clone(),
+                    receiver_id.clone(),
+                    U128(amount),
+                );
+            } else {
+                Promise::new(receiver_id.clone()).transfer(amount);
+            }
+        }
+
+        //keep whatever couldn't be split evenly in the treasury for the next withdrawal
+        if balance > paid_out {
+            self.treasury.insert(&currency, &(balance - paid_out));
+        }
+
+        U128(paid_out)
+    }
+
+    /// views
+    //return the commission configuration of the market
+    pub fn get_market_fee_config(&self) -> MarketFeeConfig {
+        MarketFeeConfig {
+            market_fee: self.market_fee,
+            fee_recipients: self.fee_recipients.clone(),
+        }
+    }
+
+    //quote the commission the market takes for a given price. If an nft contract is passed in, its fee override is used.
+    //rounding can add a few yocto on top of this
+    pub fn get_market_fee(&self, price: U128, nft_contract_id: Option<AccountId>) -> U128 {
+        let market_fee = nft_contract_id
+            .map(|nft_contract_id| self.internal_collection_market_fee(&nft_contract_id))
+            .unwrap_or(self.market_fee);
+        U128(internal_market_fee(market_fee, price.0))
+    }
+
+    //return the commission collected in a currency (NEAR if no FT contract ID is passed in) that wasn't withdrawn yet
+    pub fn get_treasury_balance(&self, ft_token_id: Option<FungibleTokenId>) -> U128 {
+        U128(self.treasury.get(&currency_for(ft_token_id)).unwrap_or(0))
+    }
+}
+
+//makes sure a commission (in basis points) is within what the market can take. Used for the market fee and
+//for the fee overrides of registered collections
+pub(crate) fn assert_market_fee(market_fee: u32) {
+    assert!(
+        market_fee <= MAX_MARKET_FEE,
+        "Market fee cannot be more than {} basis points",
+        MAX_MARKET_FEE
+    );
+}
+
+//calculates the commission taken for a given price with a given fee (in basis points)
+pub(crate) fn internal_market_fee(market_fee: u32, price: Balance) -> Balance {
+    price * (market_fee as u128) / (MAX_BASIS_POINTS as u128)
+}
+
+impl Contract {
+    //takes the market commission (or the fee override of the nft contract) out of a verified payout before it is distributed.
+    //every receiver's share is reduced proportionally and whatever is left of the price is added to the treasury for the currency
+    pub(crate) fn internal_take_market_fee(
+        &mut self,
+        nft_contract_id: &AccountId,
+        currency: &Currency,
+        price: U128,
+        payout: HashMap<AccountId, U128>,
+    ) -> HashMap<AccountId, U128> {
+        let market_fee = self.internal_collection_market_fee(nft_contract_id);
+        if market_fee == 0 {
+            return payout;
+        }
+
+        //every receiver keeps what's left of their share once the commission is taken
+        let net_share = (MAX_BASIS_POINTS - market_fee) as u128;
+
+        let mut distributed = 0;
+        let payout: HashMap<AccountId, U128> = payout
+            .into_iter()
+            .map(|(receiver_id, amount)| {
+                let net_amount = amount.0 * net_share / (MAX_BASIS_POINTS as u128);
+                distributed += net_amount;
+                (receiver_id, U128(net_amount))
+            })
+            .collect();
+
+        //the commission (plus any rounding dust) goes to the treasury
+        let collected = self.treasury.get(currency).unwrap_or(0);
+        self.treasury
+            .insert(currency, &(collected + price.0 - distributed));
+
+        payout
+    }
+}
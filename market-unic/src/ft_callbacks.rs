@@ -0,0 +1,298 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+/// transfer callbacks from FT Contracts
+
+//struct for the purchase parameters passed in the msg of ft_transfer_call
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PurchaseArgs {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+}
+
+//struct for keeping track of a purchase paid in fungible tokens while the nft contract transfers the token
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtPurchase {
+    //fungible token contract the purchase is paid with
+    pub ft_token_id: FungibleTokenId,
+    //nft contract where the token was minted
+    pub nft_contract_id: AccountId,
+    pub buyer_id: AccountId,
+    pub seller_id: AccountId,
+    //price of the sale in the fungible token
+    pub price: U128,
+    //amount of fungible tokens sent by the buyer
+    pub amount: U128,
+    pub token_id: TokenId,
+    //whether the market holds the token in custody
+    pub escrowed: bool,
+}
+
+/*
+    trait that will be used as the callback from the FT contract. When ft_transfer_call is
+    called with the market as the receiver, it will fire a cross contract call to this marketplace
+    and this is the function that is invoked.
+*/
+trait FungibleTokenReceiver {
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128>;
+}
+
+//implementation of the trait
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// where we purchase a sale with fungible tokens. Returns the amount of tokens that should be refunded to the buyer
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // get the fungible token contract ID which is the predecessor
+        let ft_token_id = env::predecessor_account_id();
+        //make sure the market accepts payments in this fungible token
+        assert!(
+            self.ft_token_ids.contains(&ft_token_id),
+            "Fungible token {} is not accepted by the market",
+            ft_token_id
+        );
+
+        //the sale to purchase comes from the msg field. If the msg isn't valid, it panics and the tokens are refunded
+        let PurchaseArgs {
+            nft_contract_id,
+            token_id,
+        } = near_sdk::serde_json::from_str(&msg).expect("Not valid PurchaseArgs");
+
+        //get the unique sale ID (contract + DELIMITER + token ID)
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+
+        //get the sale object from the unique sale ID. If the sale doesn't exist, panic.
+        let sale = self.sales.get(&contract_and_token_id).expect("No sale");
+
+        //the buyer is the account that sent the fungible tokens and they can't be the owner of the sale
+        let buyer_id = sender_id;
+        assert_ne!(sale.owner_id, buyer_id, "Cannot bid on your own sale.");
+        //make sure the listing hasn't expired
+        assert!(!sale_is_expired(&sale), "Sale has expired");
+        //make sure the buyer is allowed to purchase a private sale
+        assert_sale_reserved_for(&sale, &buyer_id);
+
+        //get the price of the token in the fungible token that was sent
+        let price = sale
+            .sale_conditions
+            .get(ft_token_id.as_str())
+            .expect("Sale is not priced in this fungible token")
+            .0;
+
+        //make sure the amount sent covers the price
+        assert!(
+            amount.0 >= price,
+            "Transferred amount must be greater than or equal to the current price: {:?}",
+            price
+        );
+
+        //process the purchase (which will remove the sale, transfer and get the payout from the nft contract, and then distribute royalties)
+        PromiseOrValue::Promise(self.internal_process_ft_purchase(
+            ft_token_id,
+            nft_contract_id,
+            token_id,
+            U128(price),
+            amount,
+            buyer_id,
+        ))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /*
+        private method used to resolve the promise when calling nft_transfer_payout for a purchase paid in fungible tokens.
+        If everything is fine, it will pay the accounts with ft_transfer calls. It returns the amount of fungible tokens
+        that the fungible token contract should refund to the buyer (everything if there was a problem, the excess otherwise).
+    */
+    #[private]
+    pub fn resolve_ft_purchase(&mut self, purchase: FtPurchase) -> U128 {
+        let FtPurchase {
+            ft_token_id,
+            nft_contract_id,
+            buyer_id,
+            seller_id,
+            price,
+            amount,
+            token_id,
+            escrowed,
+        } = purchase;
+
+        // checking for payout information returned from the nft_transfer_payout method
+        let payout = if let Some(payout) = verify_payout(price, &seller_id) {
+            payout
+        //if the payout option was None, the fungible token contract refunds the buyer everything they sent
+        } else {
+            env::log_str(&format!(
+                "Refunding {} {} to {}",
+                amount.0, ft_token_id, buyer_id
+            ));
+            //the market still holds escrowed tokens so we give them back to the seller
+            if escrowed {
+                self.internal_return_escrowed_token(nft_contract_id, token_id, seller_id);
+            }
+            return amount;
+        };
+
+        //take the market commission before the royalties are distributed
+        let payout = self.internal_take_market_fee(
+            &nft_contract_id,
+            &ft_token_id.to_string(),
+            price,
+            payout,
+        );
+
+        // FT payouts. A payout that fails (for example because the receiver isn't registered with the
+        //fungible token contract) can be withdrawn later through withdraw_ft_balance
+        for (receiver_id, payout_amount) in payout {
+            internal_ft_payout(ft_token_id.clone(), receiver_id, payout_amount);
+        }
+
+        //return the excess sent by the buyer so the fungible token contract refunds it
+        U128(amount.0 - price.0)
+    }
+
+    //withdraws the fungible tokens the market owes the caller because an earlier payout failed
+    #[payable]
+    pub fn withdraw_ft_balance(&mut self, ft_token_id: FungibleTokenId) -> Promise {
+        //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+        let balance = self
+            .ft_balances
+            .remove(&(account_id.clone(), ft_token_id.clone()))
+            .expect("No fungible token balance to withdraw");
+
+        //if the transfer fails again, the balance is recorded again
+        internal_ft_payout(ft_token_id, account_id, U128(balance))
+    }
+
+    //private method used to resolve a payout in fungible tokens. If the transfer failed, the amount
+    //is added to the receiver's balance so it can be withdrawn later. Returns true if the payout went through
+    #[private]
+    pub fn resolve_ft_payout(
+        &mut self,
+        ft_token_id: FungibleTokenId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+
+        env::log_str(&format!(
+            "Could not pay {} {} to {}, it can be withdrawn through withdraw_ft_balance",
+            amount.0, ft_token_id, receiver_id
+        ));
+        let key = (receiver_id, ft_token_id);
+        let balance = self.ft_balances.get(&key).unwrap_or(0);
+        self.ft_balances.insert(&key, &(balance + amount.0));
+        false
+    }
+
+    /// views
+    //returns the fungible tokens the market owes an account because a payout failed
+    pub fn get_ft_balance(&self, account_id: AccountId, ft_token_id: FungibleTokenId) -> U128 {
+        U128(
+            self.ft_balances
+                .get(&(account_id, ft_token_id))
+                .unwrap_or(0),
+        )
+    }
+}
+
+//pays an account in fungible tokens and records the amount as withdrawable if the transfer fails
+pub(crate) fn internal_ft_payout(
+    ft_token_id: FungibleTokenId,
+    receiver_id: AccountId,
+    amount: U128,
+) -> Promise {
+    ext_ft_contract::ft_transfer(
+        receiver_id.clone(),
+        amount,
+        Some("payout from market".to_string()),
+        ft_token_id.clone(),
+        1,
+        GAS_FOR_FT_TRANSFER,
+    )
+    .then(ext_self::resolve_ft_payout(
+        ft_token_id,
+        receiver_id,
+        amount,
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_FOR_RESOLVE_FT_PAYOUT,
+    ))
+}
+
+impl Contract {
+    //internal method used when a sale is purchased with fungible tokens.
+    //this will remove the sale, transfer and get the payout from the nft contract, and then distribute royalties
+    pub(crate) fn internal_process_ft_purchase(
+        &mut self,
+        ft_token_id: FungibleTokenId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        price: U128,
+        amount: U128,
+        buyer_id: AccountId,
+    ) -> Promise {
+        //get the sale object by removing the sale
+        let sale = self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+
+        //initiate a cross contract call to the nft contract. This will transfer the token to the buyer and return
+        //a payout object used for the market to distribute funds to the appropriate accounts.
+        ext_contract::nft_transfer_payout(
+            buyer_id.clone(),
+            token_id.clone(),
+            sale.approval_id,
+            "payout from market".to_string(),
+            price,
+            10,
+            nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        //resolve the purchase by paying the accounts in the fungible token
+        .then(ext_self::resolve_ft_purchase(
+            FtPurchase {
+                ft_token_id,
+                nft_contract_id,
+                buyer_id,
+                seller_id: sale.owner_id,
+                price,
+                amount,
+                token_id,
+                escrowed: sale.escrowed,
+            },
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_FT_PURCHASE,
+        ))
+    }
+}
+
+//this is the cross contract call that we call on our own contract.
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn resolve_ft_purchase(&mut self, purchase: FtPurchase) -> U128;
+
+    fn resolve_ft_payout(
+        &mut self,
+        ft_token_id: FungibleTokenId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> bool;
+}
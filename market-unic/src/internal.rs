@@ -1,4 +1,5 @@
 use crate::*;
+use near_sdk::promise_result_as_success;
 
 //used to generate a unique prefix in our storage collections (this is to avoid data collisions)
 pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
@@ -9,7 +10,248 @@ pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
     hash
 }
 
+//used to generate a unique prefix for collections keyed by a unique sale ID (contract + DELIMITER + token ID)
+pub(crate) fn hash_contract_and_token_id(contract_and_token_id: &str) -> CryptoHash {
+    //get the default hash
+    let mut hash = CryptoHash::default();
+    //we hash the unique sale ID and return it
+    hash.copy_from_slice(&env::sha256(contract_and_token_id.as_bytes()));
+    hash
+}
+
+//returns the currency key used in sale conditions for NEAR
+pub(crate) fn near_currency() -> Currency {
+    NEAR.to_string()
+}
+
+//returns the currency key for a fungible token contract, or for NEAR if no fungible token is passed in
+pub(crate) fn currency_for(ft_token_id: Option<FungibleTokenId>) -> Currency {
+    ft_token_id
+        .map(|ft_token_id| ft_token_id.to_string())
+        .unwrap_or_else(near_currency)
+}
+
+//checks the payout object returned from the nft_transfer_payout method. Returns None if something went wrong
+//and the buyer should be refunded. For tokens held in escrow the nft contract pays the market (the current owner)
+//so that share is given back to the seller.
+pub(crate) fn verify_payout(
+    price: U128,
+    seller_id: &AccountId,
+) -> Option<HashMap<AccountId, U128>> {
+    promise_result_as_success().and_then(|value| verify_payout_value(&value, price, seller_id))
+}
+
+//checks a serialized payout object returned by the nft contract against the price it was computed for
+pub(crate) fn verify_payout_value(
+    value: &[u8],
+    price: U128,
+    seller_id: &AccountId,
+) -> Option<HashMap<AccountId, U128>> {
+    near_sdk::serde_json::from_slice::<Payout>(value)
+        //converts the result to an optional value
+        .ok()
+        //returns None if the none. Otherwise executes the following logic
+        .and_then(|payout_object| {
+            //we'll check if length of the payout object is > 10 or it's empty. In either case, we return None
+            if payout_object.payout.len() > 10 || payout_object.payout.is_empty() {
+                env::log_str("Cannot have more than 10 royalties");
+                None
+
+            //if the payout object is the correct length, we move forward
+            } else {
+                //we'll keep track of how much the nft contract wants us to payout. Starting at the full price payed by the buyer
+                let mut remainder = price.0;
+
+                //loop through the payout and subtract the values from the remainder.
+                for &value in payout_object.payout.values() {
+                    //checked sub checks for overflow or any errors and returns None if there are problems
+                    remainder = remainder.checked_sub(value.0)?;
+                }
+                //Check to see if the NFT contract sent back a faulty payout that requires us to pay more or too little.
+                //The remainder will be 0 if the payout summed to the total price. The remainder will be 1 if the royalties
+                //we something like 3333 + 3333 + 3333.
+                if remainder == 0 || remainder == 1 {
+                    let mut payout = payout_object.payout;
+                    //give the market's share (as the custodian of an escrowed token) to the seller
+                    if let Some(market_share) = payout.remove(&env::current_account_id()) {
+                        let seller_share = payout.get(seller_id).map(|a| a.0).unwrap_or(0);
+                        payout.insert(seller_id.clone(), U128(seller_share + market_share.0));
+                    }
+                    //set the payout_option to be the payout because nothing went wrong
+                    Some(payout)
+                } else {
+                    //if the remainder was anything but 1 or 0, we return None
+                    None
+                }
+            }
+        })
+}
+
 impl Contract {
+    //make sure that the caller is the owner of the contract
+    pub(crate) fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only the contract owner can call this method"
+        );
+    }
+
+    //make sure that a sale has at least one price and every currency it is priced in is accepted by the market
+    pub(crate) fn assert_sale_conditions_supported(&self, sale_conditions: &SaleConditions) {
+        assert!(
+            !sale_conditions.is_empty(),
+            "Sale must have at least one price"
+        );
+        for currency in sale_conditions.keys() {
+            self.assert_currency_supported(currency);
+        }
+    }
+
+    //make sure that the currency is NEAR or a fungible token accepted by the market
+    pub(crate) fn assert_currency_supported(&self, currency: &Currency) {
+        let is_supported_ft = currency
+            .parse::<FungibleTokenId>()
+            .map(|ft_token_id| self.ft_token_ids.contains(&ft_token_id))
+            .unwrap_or(false);
+        assert!(
+            currency == &near_currency() || is_supported_ft,
+            "Fungible token {} is not accepted by the market",
+            currency
+        );
+    }
+
+    //returns how many entries (sales, auctions, bundles, swaps and offers) an account is currently paying storage for
+    pub(crate) fn internal_storage_entries(&self, account_id: &AccountId) -> u64 {
+        let sales = self.get_supply_by_owner_id(account_id.clone()).0;
+        let offers = self
+            .offers_by_buyer_id
+            .get(account_id)
+            .map(|offers| offers.len())
+            .unwrap_or(0);
+        let collection_offers = self
+            .collection_offers_by_buyer_id
+            .get(account_id)
+            .map(|collection_offers| collection_offers.len())
+            .unwrap_or(0);
+        let auctions = self.get_supply_auctions_by_owner_id(account_id.clone()).0;
+        let bundles = self
+            .bundles_by_owner_id
+            .get(account_id)
+            .map(|bundles| bundles.len())
+            .unwrap_or(0);
+        let swaps = self
+            .swaps_by_maker_id
+            .get(account_id)
+            .map(|swaps| swaps.len())
+            .unwrap_or(0);
+        sales + auctions + bundles + swaps + offers + collection_offers
+    }
+
+    //make sure that the account has paid enough storage to cover their current entries plus `new_entries` more.
+    pub(crate) fn internal_assert_storage(&self, account_id: &AccountId, new_entries: u64) {
+        //get the storage for a sale. dot 0 converts from U128 to u128
+        let storage_amount = self.storage_minimum_balance().0;
+        //get the total storage paid by the account
+        let paid_storage = self.storage_deposits.get(account_id).unwrap_or(0);
+        //get the storage required which is simply the storage for the number of entries they have + the new ones
+        let storage_required =
+            (self.internal_storage_entries(account_id) + new_entries) as u128 * storage_amount;
+
+        //make sure that the total paid is >= the required storage
+        assert!(
+            paid_storage >= storage_required,
+            "Insufficient storage paid: {}, for {} sales at {} rate of per sale",
+            paid_storage,
+            storage_required / STORAGE_PER_SALE,
+            STORAGE_PER_SALE
+        );
+    }
+
+    //internal method for adding a sale to the market and populating the collections necessary for the view calls
+    pub(crate) fn internal_add_sale(&mut self, sale: Sale) {
+        let owner_id = sale.owner_id.clone();
+        let nft_contract_id: AccountId = sale.nft_contract_id.parse().unwrap();
+        let token_id = sale.token_id.clone();
+
+        //create the unique sale ID which is the contract + DELIMITER + token ID
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+
+        //insert the key value pair into the sales map. Key is the unique ID. value is the sale object
+        self.sales.insert(&contract_and_token_id, &sale);
+
+        //get the sales by owner ID for the given owner. If there are none, we create a new empty set
+        let mut by_owner_id = self.by_owner_id.get(&owner_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::ByOwnerIdInner {
+                    //we get a new unique prefix for the collection by hashing the owner
+                    account_id_hash: hash_account_id(&owner_id),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+
+        //insert the unique sale ID into the set
+        by_owner_id.insert(&contract_and_token_id);
+        //insert that set back into the collection for the owner
+        self.by_owner_id.insert(&owner_id, &by_owner_id);
+
+        //get the token IDs for the given nft contract ID. If there are none, we create a new empty set
+        let mut by_nft_contract_id = self
+            .by_nft_contract_id
+            .get(&nft_contract_id)
+            .unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::ByNFTContractIdInner {
+                        //we get a new unique prefix for the collection by hashing the owner
+                        account_id_hash: hash_account_id(&nft_contract_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+
+        //insert the token ID into the set
+        by_nft_contract_id.insert(&token_id);
+        //insert the set back into the collection for the given nft contract ID
+        self.by_nft_contract_id
+            .insert(&nft_contract_id, &by_nft_contract_id);
+
+        //if the sale is private, index it by the account it's reserved for
+        if let Some(reserved_for) = &sale.reserved_for {
+            let mut private_sales = self
+                .private_sales_by_buyer_id
+                .get(reserved_for)
+                .unwrap_or_else(|| {
+                    UnorderedSet::new(
+                        StorageKey::PrivateSalesByBuyerIdInner {
+                            //we get a new unique prefix for the collection by hashing the buyer
+                            account_id_hash: hash_account_id(reserved_for),
+                        }
+                        .try_to_vec()
+                        .unwrap(),
+                    )
+                });
+            private_sales.insert(&contract_and_token_id);
+            self.private_sales_by_buyer_id
+                .insert(reserved_for, &private_sales);
+        }
+    }
+
+    //internal method for removing the sale of a token (if it's listed) once the token was sold another way,
+    //for example by accepting an offer. This gives the seller their storage back
+    pub(crate) fn internal_remove_stale_sale(
+        &mut self,
+        nft_contract_id: &AccountId,
+        token_id: &TokenId,
+    ) {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        if self.sales.get(&contract_and_token_id).is_some() {
+            self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+        }
+    }
+
     //internal method for removing a sale from the market. This returns the previously removed sale object
     pub(crate) fn internal_remove_sale(
         &mut self,
@@ -55,10 +297,128 @@ impl Contract {
                 .insert(&nft_contract_id, &by_nft_contract_id);
         }
 
+        //if the sale was private, remove it from the private sales of the account it was reserved for
+        if let Some(reserved_for) = &sale.reserved_for {
+            let mut private_sales = self
+                .private_sales_by_buyer_id
+                .get(reserved_for)
+                .expect("No private sale by buyer_id");
+            private_sales.remove(&contract_and_token_id);
+            if private_sales.is_empty() {
+                self.private_sales_by_buyer_id.remove(reserved_for);
+            } else {
+                self.private_sales_by_buyer_id
+                    .insert(reserved_for, &private_sales);
+            }
+        }
+
         //return the sale object
         sale
     }
 
+    //internal method for adding an auction to the market and populating the collections necessary for the view calls
+    pub(crate) fn internal_add_auction(&mut self, auction: Auction) {
+        let account_id = auction.owner_id.clone();
+        let nft_contract_id: AccountId = auction.nft_contract_id.parse().unwrap();
+        let auction_token = auction.auction_token.clone();
+
+        let contract_and_auction_token_id =
+            format!("{}{}{}", nft_contract_id, DELIMETER, auction_token);
+
+        //an existing auction may still hold the escrowed bid of its winner so it must be settled or removed first
+        assert!(
+            self.auctions.get(&contract_and_auction_token_id).is_none(),
+            "An auction already exists for this token"
+        );
+        self.auctions
+            .insert(&contract_and_auction_token_id, &auction);
+
+        //get the auctions by owner ID for the given owner. If there are none, we create a new empty set
+        let mut by_auction_owner_id =
+            self.by_auction_owner_id
+                .get(&account_id)
+                .unwrap_or_else(|| {
+                    UnorderedSet::new(
+                        StorageKey::ByAuctionOwnerIdInner {
+                            //we get a new unique prefix for the collection by hashing the owner account
+                            account_id_hash: hash_account_id(&account_id),
+                        }
+                        .try_to_vec()
+                        .unwrap(),
+                    )
+                });
+
+        by_auction_owner_id.insert(&contract_and_auction_token_id);
+
+        self.by_auction_owner_id
+            .insert(&account_id, &by_auction_owner_id);
+
+        //get the auction token IDs for the given nft contract ID. If there are none, we create a new empty set
+        let mut auctions_by_nft_contract_id = self
+            .auctions_by_nft_contract_id
+            .get(&nft_contract_id)
+            .unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::AuctionsByNFTContractIdInner {
+                        //we get a new unique prefix for the collection by hashing the owner
+                        account_id_hash: hash_account_id(&nft_contract_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+
+        //insert the token ID into the set
+        auctions_by_nft_contract_id.insert(&auction_token);
+
+        self.auctions_by_nft_contract_id
+            .insert(&nft_contract_id, &auctions_by_nft_contract_id);
+    }
+
+    //internal method for giving a token held in escrow back to its owner (when a listing is taken down).
+    //if the transfer fails, the owner can claim the token later through claim_escrowed_token
+    pub(crate) fn internal_return_escrowed_token(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+    ) -> Promise {
+        self.internal_send_escrowed_token(
+            nft_contract_id,
+            token_id,
+            owner_id,
+            "returned from market escrow",
+        )
+    }
+
+    //internal method for sending a token held in escrow to the given account. If the transfer fails,
+    //the account can claim the token later through claim_escrowed_token
+    pub(crate) fn internal_send_escrowed_token(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        receiver_id: AccountId,
+        memo: &str,
+    ) -> Promise {
+        ext_contract::nft_transfer(
+            receiver_id.clone(),
+            token_id.clone(),
+            None,
+            Some(memo.to_string()),
+            nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        .then(crate::escrow::ext_self::resolve_escrowed_token_return(
+            nft_contract_id,
+            token_id,
+            receiver_id,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_TOKEN_RETURN,
+        ))
+    }
+
     pub(crate) fn internal_remove_auction(
         &mut self,
         nft_contract_id: AccountId,
@@ -66,6 +426,9 @@ impl Contract {
     ) -> Auction {
         let contract_and_token_id = format!("{}{}{}", &nft_contract_id, DELIMETER, token_id);
 
+        //settle the sealed bids that weren't revealed while the auction is still there
+        self.internal_release_sealed_bids(&contract_and_token_id);
+
         let auction = self
             .auctions
             .remove(&contract_and_token_id)
@@ -103,6 +466,170 @@ impl Contract {
             self.auctions_by_nft_contract_id
                 .insert(&nft_contract_id, &auctions_by_nft_contract_id);
         }
+
+        //the bid history is only kept for live auctions
+        self.internal_remove_bid_history(&contract_and_token_id);
+
         auction
     }
+
+    //internal method for adding an offer to the market and populating the collections necessary for the view calls
+    pub(crate) fn internal_add_offer(&mut self, offer: Offer) {
+        let buyer_id = offer.buyer_id.clone();
+        let nft_contract_id: AccountId = offer.nft_contract_id.parse().unwrap();
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, offer.token_id);
+        let offer_id = offer_id(&nft_contract_id, &offer.token_id, &buyer_id);
+
+        self.offers.insert(&offer_id, &offer);
+
+        //get the buyers with an offer on the token. If there are none, we create a new empty set
+        let mut offers_by_token = self
+            .offers_by_token
+            .get(&contract_and_token_id)
+            .unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::OffersByTokenInner {
+                        //we get a new unique prefix for the collection by hashing the unique sale ID
+                        token_hash: hash_contract_and_token_id(&contract_and_token_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+        offers_by_token.insert(&buyer_id);
+        self.offers_by_token
+            .insert(&contract_and_token_id, &offers_by_token);
+
+        //get the offers for the given buyer. If there are none, we create a new empty set
+        let mut offers_by_buyer_id = self.offers_by_buyer_id.get(&buyer_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::OffersByBuyerIdInner {
+                    //we get a new unique prefix for the collection by hashing the buyer
+                    account_id_hash: hash_account_id(&buyer_id),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        offers_by_buyer_id.insert(&offer_id);
+        self.offers_by_buyer_id
+            .insert(&buyer_id, &offers_by_buyer_id);
+    }
+
+    //internal method for removing an offer from the market. This returns the previously removed offer object
+    pub(crate) fn internal_remove_offer(&mut self, offer_id: &OfferId) -> Offer {
+        let offer = self.offers.remove(offer_id).expect("No offer");
+        let contract_and_token_id =
+            format!("{}{}{}", offer.nft_contract_id, DELIMETER, offer.token_id);
+
+        //remove the buyer from the set of buyers with an offer on the token
+        let mut offers_by_token = self
+            .offers_by_token
+            .get(&contract_and_token_id)
+            .expect("No offer by token");
+        offers_by_token.remove(&offer.buyer_id);
+        if offers_by_token.is_empty() {
+            self.offers_by_token.remove(&contract_and_token_id);
+        } else {
+            self.offers_by_token
+                .insert(&contract_and_token_id, &offers_by_token);
+        }
+
+        //remove the offer ID from the set of offers for the buyer
+        let mut offers_by_buyer_id = self
+            .offers_by_buyer_id
+            .get(&offer.buyer_id)
+            .expect("No offer by buyer_id");
+        offers_by_buyer_id.remove(offer_id);
+        if offers_by_buyer_id.is_empty() {
+            self.offers_by_buyer_id.remove(&offer.buyer_id);
+        } else {
+            self.offers_by_buyer_id
+                .insert(&offer.buyer_id, &offers_by_buyer_id);
+        }
+
+        offer
+    }
+
+    //internal method for adding a collection offer to the market and the indexes for its nft contract and buyer
+    pub(crate) fn internal_add_collection_offer(&mut self, offer: CollectionOffer) {
+        let buyer_id = offer.buyer_id.clone();
+        let nft_contract_id: AccountId = offer.nft_contract_id.parse().unwrap();
+
+        self.collection_offers.insert(&offer.offer_id, &offer);
+
+        //get the collection offers for the given nft contract. If there are none, we create a new empty set
+        let mut offers_by_nft_contract_id = self
+            .collection_offers_by_nft_contract_id
+            .get(&nft_contract_id)
+            .unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::CollectionOffersByNFTContractIdInner {
+                        //we get a new unique prefix for the collection by hashing the nft contract
+                        account_id_hash: hash_account_id(&nft_contract_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+        offers_by_nft_contract_id.insert(&offer.offer_id);
+        self.collection_offers_by_nft_contract_id
+            .insert(&nft_contract_id, &offers_by_nft_contract_id);
+
+        //get the collection offers for the given buyer. If there are none, we create a new empty set
+        let mut offers_by_buyer_id = self
+            .collection_offers_by_buyer_id
+            .get(&buyer_id)
+            .unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::CollectionOffersByBuyerIdInner {
+                        //we get a new unique prefix for the collection by hashing the buyer
+                        account_id_hash: hash_account_id(&buyer_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+        offers_by_buyer_id.insert(&offer.offer_id);
+        self.collection_offers_by_buyer_id
+            .insert(&buyer_id, &offers_by_buyer_id);
+    }
+
+    //internal method for removing a collection offer from the market. This returns the previously removed offer object
+    pub(crate) fn internal_remove_collection_offer(&mut self, offer_id: u64) -> CollectionOffer {
+        let offer = self
+            .collection_offers
+            .remove(&offer_id)
+            .expect("No collection offer");
+        let nft_contract_id: AccountId = offer.nft_contract_id.parse().unwrap();
+
+        //remove the offer ID from the set of collection offers for the nft contract
+        let mut offers_by_nft_contract_id = self
+            .collection_offers_by_nft_contract_id
+            .get(&nft_contract_id)
+            .expect("No collection offer by nft_contract_id");
+        offers_by_nft_contract_id.remove(&offer_id);
+        if offers_by_nft_contract_id.is_empty() {
+            self.collection_offers_by_nft_contract_id
+                .remove(&nft_contract_id);
+        } else {
+            self.collection_offers_by_nft_contract_id
+                .insert(&nft_contract_id, &offers_by_nft_contract_id);
+        }
+
+        //remove the offer ID from the set of collection offers for the buyer
+        let mut offers_by_buyer_id = self
+            .collection_offers_by_buyer_id
+            .get(&offer.buyer_id)
+            .expect("No collection offer by buyer_id");
+        offers_by_buyer_id.remove(&offer_id);
+        if offers_by_buyer_id.is_empty() {
+            self.collection_offers_by_buyer_id.remove(&offer.buyer_id);
+        } else {
+            self.collection_offers_by_buyer_id
+                .insert(&offer.buyer_id, &offers_by_buyer_id);
+        }
+
+        offer
+    }
 }
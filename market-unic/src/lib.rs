@@ -1,45 +1,110 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     assert_one_yocto, env, ext_contract, near_bindgen, AccountId, Balance, BorshStorageKey,
-    CryptoHash, Gas, PanicOnDefault, Promise,
+    CryptoHash, Gas, PanicOnDefault, Promise, PromiseOrValue,
 };
 use std::collections::HashMap;
 
 use crate::auction_execute::*;
+use crate::bid_history::*;
+use crate::bundle::*;
+use crate::collection_offer::*;
+use crate::collection_registry::*;
+use crate::dutch_auction::*;
+use crate::events::*;
 use crate::external::*;
 use crate::internal::*;
+use crate::offer::*;
 use crate::sale::*;
+use crate::sealed_bid::*;
+use crate::swap::*;
 use near_sdk::env::STORAGE_PRICE_PER_BYTE;
 
 mod auction_callback;
+mod auction_claim;
 mod auction_execute;
 mod auction_view;
+mod bid_history;
+mod bundle;
+mod bundle_views;
+mod collection_offer;
+mod collection_offer_views;
+mod collection_registry;
+mod dutch_auction;
+mod escrow;
+mod events;
 mod external;
+mod fees;
+mod ft_callbacks;
 mod internal;
+mod migrate;
 mod nft_callbacks;
+mod offer;
+mod offer_views;
 mod sale;
 mod sale_views;
+mod sealed_bid;
+mod swap;
+mod swap_views;
 
 //GAS constants to attach to calls
 const GAS_FOR_RESOLVE_PURCHASE: Gas = Gas(115_000_000_000_000);
 const GAS_FOR_NFT_TRANSFER: Gas = Gas(15_000_000_000_000);
-const ENROLL_FEE: Balance = 1_000_000_000_000_000_000_000_00;
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_FT_PAYOUT: Gas = Gas(5_000_000_000_000);
+//enough to pay every payout receiver in fungible tokens with a callback for each transfer
+const GAS_FOR_RESOLVE_FT_PURCHASE: Gas = Gas(160_000_000_000_000);
+const GAS_FOR_NFT_AUCTION_CHECK: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_CANCELLED_AUCTION: Gas = Gas(20_000_000_000_000);
+//enough to send every token of the largest bundle on (or back) with a callback for each transfer
+const GAS_FOR_RESOLVE_BUNDLE_PAYOUT: Gas = Gas(110_000_000_000_000);
+//enough to hand both tokens of a swap over and check that they arrived
+const GAS_FOR_RESOLVE_SWAP: Gas = Gas(100_000_000_000_000);
+//enough to give both tokens of a swap back with a callback for each transfer
+const GAS_FOR_RESOLVE_SWAP_DELIVERY: Gas = Gas(55_000_000_000_000);
+const GAS_FOR_RESOLVE_TOKEN_RETURN: Gas = Gas(10_000_000_000_000);
+
+//the largest bundle the market can settle in one transaction (this is limited by GAS)
+const MAX_BUNDLE_SIZE: usize = 4;
 const NO_DEPOSIT: Balance = 0;
 
+//how long (in nanoseconds) the nft contract keeps an auction approval valid after the auction ends so it
+//can be settled. This has to match the settlement period of the nft contract
+const AUCTION_SETTLEMENT_PERIOD: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+
+//the commission is expressed in basis points of the sale price (10_000 is 100%)
+const MAX_BASIS_POINTS: u32 = 10_000;
+//the highest commission the market (or a collection override) can take (10%)
+const MAX_MARKET_FEE: u32 = 1_000;
+
 //the minimum storage to have a sale on the contract.
 const STORAGE_PER_SALE: u128 = 1000 * STORAGE_PRICE_PER_BYTE;
 
 //every sale will have a unique ID which is `CONTRACT + DELIMITER + TOKEN_ID`
 static DELIMETER: &str = ".";
 
+//the currency key used in sale conditions for prices in yoctoNEAR. Account IDs are lowercase
+//so this key can never be the ID of a fungible token contract
+static NEAR: &str = "NEAR";
+
+//the standard name and version used for the events logged by the market
+pub const MARKET_STANDARD_NAME: &str = "unic_market";
+pub const MARKET_EVENT_VERSION: &str = "1.0.0";
+
 //Creating custom types to use within the contract. This makes things more readable.
 pub type SalePriceInYoctoNear = U128;
 pub type TokenId = String;
 pub type FungibleTokenId = AccountId;
+//a currency is either NEAR (the NEAR key) or the ID of a fungible token contract
+pub type Currency = String;
 pub type ContractAndTokenId = String;
+//every offer has a unique ID which is the hash of the (CONTRACT, TOKEN_ID, BUYER_ID) tuple
+pub type OfferId = CryptoHash;
+//the prices a sale is listed for. Keyed by currency which is either NEAR or an FT contract ID
+pub type SaleConditions = HashMap<Currency, SalePriceInYoctoNear>;
 //defines the payout type we'll be parsing from the NFT contract as a part of the royalty standard.
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -75,6 +140,91 @@ pub struct Contract {
 
     //keep track of the storage that accounts have payed
     pub storage_deposits: LookupMap<AccountId, Balance>,
+
+    //keep track of the fungible token contracts that are accepted as payment for sales
+    pub ft_token_ids: UnorderedSet<FungibleTokenId>,
+
+    //commission (in basis points) the market takes from every sale and auction
+    pub market_fee: u32,
+
+    //accounts the treasury is split between when it is withdrawn (account -> share in basis points)
+    pub fee_recipients: HashMap<AccountId, u32>,
+
+    //commission collected by the market and not yet withdrawn, per currency (NEAR or an FT contract ID)
+    pub treasury: UnorderedMap<Currency, Balance>,
+
+    //keep track of the escrowed offers made on tokens (listed or not)
+    pub offers: UnorderedMap<OfferId, Offer>,
+
+    //keep track of the buyers with an offer for a given token
+    pub offers_by_token: LookupMap<ContractAndTokenId, UnorderedSet<AccountId>>,
+
+    //keep track of all the offer IDs for every buyer
+    pub offers_by_buyer_id: LookupMap<AccountId, UnorderedSet<OfferId>>,
+
+    //keep track of the escrowed offers made on any token of a collection
+    pub collection_offers: UnorderedMap<u64, CollectionOffer>,
+
+    //the ID that will be given to the next collection offer
+    pub next_collection_offer_id: u64,
+
+    //keep track of all the collection offer IDs for a given nft contract
+    pub collection_offers_by_nft_contract_id: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    //keep track of all the collection offer IDs for every buyer
+    pub collection_offers_by_buyer_id: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    //total NEAR held for live auction bids, offers and collection offers. The market never keeps
+    //any of it: it is either refunded in full or paid out, and the commission goes to the treasury
+    pub total_escrow: Balance,
+
+    //keep track of every bid placed on a live auction, in the order they were placed
+    pub bid_history: LookupMap<ContractAndTokenId, Vector<Bid>>,
+
+    //keep track of the live auctions every bidder bid on
+    pub auctions_by_bidder: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+
+    //keep track of the bids committed on sealed-bid auctions that weren't revealed yet
+    pub sealed_bids: LookupMap<ContractAndTokenId, UnorderedMap<AccountId, SealedBid>>,
+
+    //keep track of the bundles of tokens sold together for one price
+    pub bundles: UnorderedMap<u64, Bundle>,
+
+    //the ID that will be given to the next bundle
+    pub next_bundle_id: u64,
+
+    //keep track of all the bundle IDs for every owner
+    pub bundles_by_owner_id: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    //keep track of the swap offers giving a token for another token or any token of a collection
+    pub swaps: UnorderedMap<u64, Swap>,
+
+    //the ID that will be given to the next swap
+    pub next_swap_id: u64,
+
+    //keep track of the swap IDs a token is offered or wanted in
+    pub swaps_by_token: LookupMap<ContractAndTokenId, UnorderedSet<u64>>,
+
+    //keep track of the swap IDs that accept any token of a given nft contract
+    pub swaps_by_nft_contract_id: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    //keep track of all the swap IDs for every maker
+    pub swaps_by_maker_id: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    //keep track of the private sales (contract + DELIMITER + token ID) reserved for every buyer
+    pub private_sales_by_buyer_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+
+    //keep track of the NFT contracts accepted by the market and their settings
+    pub collections: UnorderedMap<AccountId, CollectionSettings>,
+
+    //whether only the registered NFT contracts can be listed. On unless the owner turns it off
+    pub registry_enforced: bool,
+
+    //keep track of the escrowed tokens (contract + DELIMITER + token ID) that couldn't be given back and who can claim them
+    pub claimable_tokens: LookupMap<ContractAndTokenId, AccountId>,
+
+    //keep track of the fungible tokens owed to every account (account, FT contract) because a payout failed
+    pub ft_balances: LookupMap<(AccountId, FungibleTokenId), Balance>,
 }
 
 /// Helper structure to for keys of the persistent collections.
@@ -94,6 +244,38 @@ pub enum StorageKey {
     ByNFTTokenTypeInner { token_type_hash: CryptoHash },
     FTTokenIds,
     StorageDeposits,
+    Treasury,
+    Offers,
+    OffersByToken,
+    OffersByTokenInner { token_hash: CryptoHash },
+    OffersByBuyerId,
+    OffersByBuyerIdInner { account_id_hash: CryptoHash },
+    CollectionOffers,
+    CollectionOffersByNFTContractId,
+    CollectionOffersByNFTContractIdInner { account_id_hash: CryptoHash },
+    CollectionOffersByBuyerId,
+    CollectionOffersByBuyerIdInner { account_id_hash: CryptoHash },
+    BidHistory,
+    BidHistoryInner { token_hash: CryptoHash },
+    AuctionsByBidder,
+    AuctionsByBidderInner { account_id_hash: CryptoHash },
+    SealedBids,
+    SealedBidsInner { token_hash: CryptoHash },
+    Bundles,
+    BundlesByOwnerId,
+    BundlesByOwnerIdInner { account_id_hash: CryptoHash },
+    Swaps,
+    SwapsByToken,
+    SwapsByTokenInner { token_hash: CryptoHash },
+    SwapsByNFTContractId,
+    SwapsByNFTContractIdInner { account_id_hash: CryptoHash },
+    SwapsByMakerId,
+    SwapsByMakerIdInner { account_id_hash: CryptoHash },
+    PrivateSalesByBuyerId,
+    PrivateSalesByBuyerIdInner { account_id_hash: CryptoHash },
+    Collections,
+    ClaimableTokens,
+    FtBalances,
 }
 
 #[near_bindgen]
@@ -105,6 +287,10 @@ impl Contract {
     */
     #[init]
     pub fn new(owner_id: AccountId) -> Self {
+        //the owner receives the whole treasury until other fee recipients are set
+        let mut fee_recipients = HashMap::new();
+        fee_recipients.insert(owner_id.clone(), MAX_BASIS_POINTS);
+
         let this = Self {
             //set the owner_id field equal to the passed in owner_id.
             owner_id,
@@ -117,6 +303,36 @@ impl Contract {
             by_nft_contract_id: LookupMap::new(StorageKey::ByNFTContractId),
             auctions_by_nft_contract_id: LookupMap::new(StorageKey::AuctionsByNFTContractId),
             storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            ft_token_ids: UnorderedSet::new(StorageKey::FTTokenIds),
+            market_fee: 0,
+            fee_recipients,
+            treasury: UnorderedMap::new(StorageKey::Treasury),
+            offers: UnorderedMap::new(StorageKey::Offers),
+            offers_by_token: LookupMap::new(StorageKey::OffersByToken),
+            offers_by_buyer_id: LookupMap::new(StorageKey::OffersByBuyerId),
+            collection_offers: UnorderedMap::new(StorageKey::CollectionOffers),
+            next_collection_offer_id: 0,
+            collection_offers_by_nft_contract_id: LookupMap::new(
+                StorageKey::CollectionOffersByNFTContractId,
+            ),
+            collection_offers_by_buyer_id: LookupMap::new(StorageKey::CollectionOffersByBuyerId),
+            total_escrow: 0,
+            bid_history: LookupMap::new(StorageKey::BidHistory),
+            auctions_by_bidder: LookupMap::new(StorageKey::AuctionsByBidder),
+            sealed_bids: LookupMap::new(StorageKey::SealedBids),
+            bundles: UnorderedMap::new(StorageKey::Bundles),
+            next_bundle_id: 0,
+            bundles_by_owner_id: LookupMap::new(StorageKey::BundlesByOwnerId),
+            swaps: UnorderedMap::new(StorageKey::Swaps),
+            next_swap_id: 0,
+            swaps_by_token: LookupMap::new(StorageKey::SwapsByToken),
+            swaps_by_nft_contract_id: LookupMap::new(StorageKey::SwapsByNFTContractId),
+            swaps_by_maker_id: LookupMap::new(StorageKey::SwapsByMakerId),
+            private_sales_by_buyer_id: LookupMap::new(StorageKey::PrivateSalesByBuyerId),
+            collections: UnorderedMap::new(StorageKey::Collections),
+            registry_enforced: true,
+            claimable_tokens: LookupMap::new(StorageKey::ClaimableTokens),
+            ft_balances: LookupMap::new(StorageKey::FtBalances),
         };
 
         //return the Contract object
@@ -167,11 +383,9 @@ impl Contract {
         //get the amount that the user has by removing them from the map. If they're not in the map, default to 0
         let mut amount = self.storage_deposits.remove(&owner_id).unwrap_or(0);
 
-        //how many sales is that user taking up currently. This returns a set
-        let sales = self.by_owner_id.get(&owner_id);
-        //get the length of that set.
-        let len = sales.map(|s| s.len()).unwrap_or_default();
-        //how much NEAR is being used up for all the current sales on the account
+        //how many sales and offers (on tokens or collections) is that user taking up currently.
+        let len = self.internal_storage_entries(&owner_id);
+        //how much NEAR is being used up for all the current sales and offers on the account
         let diff = u128::from(len) * STORAGE_PER_SALE;
 
         //the excess to withdraw is the total storage paid - storage being used up.
@@ -189,7 +403,34 @@ impl Contract {
         }
     }
 
+    //allows the owner to add fungible token contracts that can be used to pay for sales
+    #[payable]
+    pub fn add_ft_token_ids(&mut self, ft_token_ids: Vec<FungibleTokenId>) {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        for ft_token_id in ft_token_ids {
+            self.ft_token_ids.insert(&ft_token_id);
+        }
+    }
+
+    //allows the owner to stop accepting a fungible token contract as payment for sales
+    #[payable]
+    pub fn remove_ft_token_id(&mut self, ft_token_id: FungibleTokenId) {
+        //make sure the owner attaches exactly 1 yoctoNEAR for security purposes.
+        assert_one_yocto();
+        self.assert_owner();
+
+        self.ft_token_ids.remove(&ft_token_id);
+    }
+
     /// views
+    //return the fungible token contracts accepted as payment for sales
+    pub fn supported_ft_token_ids(&self) -> Vec<FungibleTokenId> {
+        self.ft_token_ids.to_vec()
+    }
+
     //return the minimum storage for 1 sale
     pub fn storage_minimum_balance(&self) -> U128 {
         U128(STORAGE_PER_SALE)
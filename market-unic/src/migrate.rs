@@ -0,0 +1,165 @@
+use crate::*;
+
+//layout of a sale before the market supported fungible tokens, escrow and the other listing options
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct SaleV1 {
+    pub owner_id: AccountId,
+    pub approval_id: u64,
+    pub nft_contract_id: String,
+    pub token_id: String,
+    pub sale_conditions: SalePriceInYoctoNear,
+}
+
+//layout of an auction before escrow accounting, claimable proceeds and the other auction options
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct AuctionV1 {
+    pub owner_id: AccountId,
+    pub auction_id: u64,
+    pub nft_contract_id: String,
+    pub auction_token: String,
+    pub sale_conditions: SalePriceInYoctoNear,
+    pub start_time: u128,
+    pub end_time: u128,
+    pub winner: Option<AccountId>,
+    pub is_near_claimed: bool,
+    pub is_nft_claimed: bool,
+}
+
+//layout of the contract state before the fees, offers, bundles, swaps and collection registry were added
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV1 {
+    pub owner_id: AccountId,
+    pub sales: UnorderedMap<ContractAndTokenId, SaleV1>,
+    pub auctions: UnorderedMap<ContractAndTokenId, AuctionV1>,
+    pub by_owner_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+    pub by_auction_owner_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+    pub by_nft_contract_id: LookupMap<AccountId, UnorderedSet<TokenId>>,
+    pub auctions_by_nft_contract_id: LookupMap<AccountId, UnorderedSet<TokenId>>,
+    pub storage_deposits: LookupMap<AccountId, Balance>,
+}
+
+#[near_bindgen]
+impl Contract {
+    //migrates the state of a market deployed with the V1 layout. The per-account indexes and storage deposits
+    //are kept as they are, the sales and auctions are rewritten with the new fields and every new collection
+    //starts empty. The sales and auctions are rewritten in a single call so this must be run while the market
+    //still has few listings
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let mut old: ContractV1 = env::state_read().expect("No state to migrate");
+
+        let old_sales = old.sales.to_vec();
+        old.sales.clear();
+        let old_auctions = old.auctions.to_vec();
+        old.auctions.clear();
+
+        //the owner receives the whole treasury until other fee recipients are set
+        let mut fee_recipients = HashMap::new();
+        fee_recipients.insert(old.owner_id.clone(), MAX_BASIS_POINTS);
+
+        let mut this = Self {
+            owner_id: old.owner_id,
+            sales: UnorderedMap::new(StorageKey::Sales),
+            auctions: UnorderedMap::new(StorageKey::Auction),
+            by_owner_id: old.by_owner_id,
+            by_auction_owner_id: old.by_auction_owner_id,
+            by_nft_contract_id: old.by_nft_contract_id,
+            auctions_by_nft_contract_id: old.auctions_by_nft_contract_id,
+            storage_deposits: old.storage_deposits,
+            ft_token_ids: UnorderedSet::new(StorageKey::FTTokenIds),
+            market_fee: 0,
+            fee_recipients,
+            treasury: UnorderedMap::new(StorageKey::Treasury),
+            offers: UnorderedMap::new(StorageKey::Offers),
+            offers_by_token: LookupMap::new(StorageKey::OffersByToken),
+            offers_by_buyer_id: LookupMap::new(StorageKey::OffersByBuyerId),
+            collection_offers: UnorderedMap::new(StorageKey::CollectionOffers),
+            next_collection_offer_id: 0,
+            collection_offers_by_nft_contract_id: LookupMap::new(
+                StorageKey::CollectionOffersByNFTContractId,
+            ),
+            collection_offers_by_buyer_id: LookupMap::new(StorageKey::CollectionOffersByBuyerId),
+            total_escrow: 0,
+            bid_history: LookupMap::new(StorageKey::BidHistory),
+            auctions_by_bidder: LookupMap::new(StorageKey::AuctionsByBidder),
+            sealed_bids: LookupMap::new(StorageKey::SealedBids),
+            bundles: UnorderedMap::new(StorageKey::Bundles),
+            next_bundle_id: 0,
+            bundles_by_owner_id: LookupMap::new(StorageKey::BundlesByOwnerId),
+            swaps: UnorderedMap::new(StorageKey::Swaps),
+            next_swap_id: 0,
+            swaps_by_token: LookupMap::new(StorageKey::SwapsByToken),
+            swaps_by_nft_contract_id: LookupMap::new(StorageKey::SwapsByNFTContractId),
+            swaps_by_maker_id: LookupMap::new(StorageKey::SwapsByMakerId),
+            private_sales_by_buyer_id: LookupMap::new(StorageKey::PrivateSalesByBuyerId),
+            collections: UnorderedMap::new(StorageKey::Collections),
+            //none of the collections already listed are registered yet. The owner turns the registry on
+            //with set_registry_enforced once they are
+            registry_enforced: false,
+            claimable_tokens: LookupMap::new(StorageKey::ClaimableTokens),
+            ft_balances: LookupMap::new(StorageKey::FtBalances),
+        };
+
+        for (contract_and_token_id, sale) in old_sales {
+            let mut sale_conditions = HashMap::new();
+            sale_conditions.insert(near_currency(), sale.sale_conditions);
+
+            this.sales.insert(
+                &contract_and_token_id,
+                &Sale {
+                    owner_id: sale.owner_id,
+                    approval_id: sale.approval_id,
+                    nft_contract_id: sale.nft_contract_id,
+                    token_id: sale.token_id,
+                    sale_conditions,
+                    escrowed: false,
+                    expires_at: None,
+                    dutch_auction: None,
+                    reserved_for: None,
+                },
+            );
+        }
+
+        for (contract_and_token_id, auction) in old_auctions {
+            //the winning bid is the current price of the auction and is held by the market until it's paid out
+            let bid_escrow = match auction.winner {
+                Some(_) => auction.sale_conditions,
+                None => U128(0),
+            };
+            if !auction.is_near_claimed {
+                this.internal_lock_escrow(bid_escrow.0);
+            }
+
+            this.auctions.insert(
+                &contract_and_token_id,
+                &Auction {
+                    owner_id: auction.owner_id,
+                    auction_id: auction.auction_id,
+                    nft_contract_id: auction.nft_contract_id,
+                    auction_token: auction.auction_token,
+                    sale_conditions: auction.sale_conditions,
+                    bid_escrow,
+                    start_time: auction.start_time,
+                    end_time: auction.end_time,
+                    winner: auction.winner,
+                    is_near_claimed: auction.is_near_claimed,
+                    is_nft_claimed: auction.is_nft_claimed,
+                    proceeds: None,
+                    escrowed: false,
+                    min_bid_increment: None,
+                    reserve_price: None,
+                    extension: None,
+                    extensions: vec![],
+                    no_bid_fallback: None,
+                    sealed_bid: None,
+                    settlement: AuctionSettlement::HighestBid,
+                    second_bid: None,
+                    buy_now_price: None,
+                },
+            );
+        }
+
+        this
+    }
+}
@@ -6,7 +6,95 @@ use crate::*;
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct SaleArgs {
-    pub sale_conditions: SalePriceInYoctoNear,
+    pub sale_conditions: Option<SaleConditionsArgs>,
+    //optional time (in seconds) after which the sale can no longer be purchased
+    pub expires_at: Option<u64>,
+    //if passed in, the token is listed as a dutch auction (in NEAR) instead of with fixed sale conditions
+    pub dutch_auction: Option<DutchAuctionArgs>,
+    //if passed in, the only account that can purchase the sale (private sale)
+    pub reserved_for: Option<AccountId>,
+}
+
+impl SaleArgs {
+    //returns the type of listing the msg creates
+    pub fn listing_type(&self) -> ListingType {
+        if self.dutch_auction.is_some() {
+            ListingType::DutchAuction
+        } else {
+            ListingType::Sale
+        }
+    }
+
+    //returns the sale conditions and the dutch auction configuration (if any) of the listing.
+    //dutch auctions are listed in NEAR with their start price as the sale conditions
+    pub fn into_listing(self) -> (SaleConditions, Option<DutchAuction>) {
+        match (self.sale_conditions, self.dutch_auction) {
+            (None, Some(dutch_auction)) => {
+                let dutch_auction = dutch_auction.into_dutch_auction();
+                let mut sale_conditions = HashMap::new();
+                sale_conditions.insert(near_currency(), dutch_auction.start_price);
+                (sale_conditions, Some(dutch_auction))
+            }
+            (Some(sale_conditions), None) => (sale_conditions.into_sale_conditions(), None),
+            _ => env::panic_str("Must pass in either sale_conditions or a dutch_auction"),
+        }
+    }
+}
+
+//the sale conditions passed in the msg. Either a single price in yoctoNEAR (the original format)
+//or a map from currency ("NEAR" or an FT contract ID) to price
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+pub enum SaleConditionsArgs {
+    Near(SalePriceInYoctoNear),
+    Currencies(SaleConditions),
+}
+
+impl SaleConditionsArgs {
+    //converts the passed in sale conditions into a map from currency to price
+    pub fn into_sale_conditions(self) -> SaleConditions {
+        match self {
+            SaleConditionsArgs::Near(price) => {
+                let mut sale_conditions = HashMap::new();
+                sale_conditions.insert(near_currency(), price);
+                sale_conditions
+            }
+            SaleConditionsArgs::Currencies(sale_conditions) => sale_conditions,
+        }
+    }
+}
+
+//actions (other than listing the token for sale) that can be passed in the msg of nft_approve
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ApprovalAction {
+    //accept the escrowed offer the given buyer made on the token
+    AcceptOffer { buyer_id: AccountId },
+    //sell the token to the collection offer with the given ID
+    FillCollectionOffer { offer_id: u64 },
+    //add the approval of the token to the bundle with the given ID
+    AddToBundle { bundle_id: u64 },
+    //open the swap with the given ID by approving the token the maker gives
+    ActivateSwap { swap_id: u64 },
+    //accept the swap with the given ID by approving a token the maker wants
+    AcceptSwap { swap_id: u64 },
+}
+
+//struct for the listing parameters passed in the msg of nft_transfer_call. If both the start
+//and end time (in seconds) are passed in, the token is listed as an auction instead of a sale.
+//the expiry (in seconds) only applies to sales since auctions already have an end time
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowArgs {
+    pub sale_conditions: SaleConditionsArgs,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    pub expires_at: Option<u64>,
+    //if passed in, the only account that can purchase the sale. Only applies to sales
+    pub reserved_for: Option<AccountId>,
+    //optional bid increment and reserve price if the token is listed as an auction
+    #[serde(flatten)]
+    pub auction_args: AuctionArgs,
 }
 
 /*
@@ -22,6 +110,28 @@ trait NonFungibleTokenApprovalsReceiver {
         approval_id: u64,
         msg: String,
     );
+
+    fn nft_on_batch_approve(
+        &mut self,
+        approvals: Vec<(TokenId, u64)>,
+        owner_id: AccountId,
+        msg: String,
+    );
+}
+
+/*
+    trait that will be used as the callback from the NFT contract. When nft_transfer_call is
+    called with the market as the receiver, the token is moved into the market's custody and
+    this is the function that is invoked.
+*/
+trait NonFungibleTokenReceiver {
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool>;
 }
 
 //implementation of the trait
@@ -50,84 +160,292 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
         //make sure the owner ID is the signer.
         assert_eq!(owner_id, signer_id, "owner_id should be signer_id");
 
-        //we need to enforce that the user has enough storage for 1 EXTRA sale.
+        //if the msg is an action instead of sale conditions, we execute it rather than listing the token
+        if let Ok(action) = near_sdk::serde_json::from_str::<ApprovalAction>(&msg) {
+            //make sure the market accepts the nft contract for the action
+            let listing_type = match action {
+                ApprovalAction::AcceptOffer { .. } | ApprovalAction::FillCollectionOffer { .. } => {
+                    ListingType::Sale
+                }
+                ApprovalAction::AddToBundle { .. } => ListingType::Bundle,
+                ApprovalAction::ActivateSwap { .. } | ApprovalAction::AcceptSwap { .. } => {
+                    ListingType::Swap
+                }
+            };
+            self.assert_collection_allows(&nft_contract_id, listing_type);
 
-        //get the storage for a sale. dot 0 converts from U128 to u128
-        let storage_amount = self.storage_minimum_balance().0;
-        //get the total storage paid by the owner
-        let owner_paid_storage = self.storage_deposits.get(&signer_id).unwrap_or(0);
-        //get the storage required which is simply the storage for the number of sales they have + 1
-        let signer_storage_required =
-            (self.get_supply_by_owner_id(signer_id).0 + 1) as u128 * storage_amount;
-
-        //make sure that the total paid is >= the required storage
-        assert!(
-            owner_paid_storage >= signer_storage_required,
-            "Insufficient storage paid: {}, for {} sales at {} rate of per sale",
-            owner_paid_storage,
-            signer_storage_required / STORAGE_PER_SALE,
-            STORAGE_PER_SALE
-        );
+            match action {
+                ApprovalAction::AcceptOffer { buyer_id } => {
+                    self.internal_accept_offer(
+                        nft_contract_id,
+                        token_id,
+                        owner_id,
+                        approval_id,
+                        buyer_id,
+                    );
+                }
+                ApprovalAction::FillCollectionOffer { offer_id } => {
+                    self.internal_fill_collection_offer(
+                        nft_contract_id,
+                        token_id,
+                        owner_id,
+                        approval_id,
+                        offer_id,
+                    );
+                }
+                ApprovalAction::AddToBundle { bundle_id } => {
+                    self.internal_approve_bundle_token(
+                        bundle_id,
+                        nft_contract_id,
+                        token_id,
+                        owner_id,
+                        approval_id,
+                    );
+                }
+                ApprovalAction::ActivateSwap { swap_id } => {
+                    self.internal_activate_swap(
+                        swap_id,
+                        nft_contract_id,
+                        token_id,
+                        owner_id,
+                        approval_id,
+                    );
+                }
+                ApprovalAction::AcceptSwap { swap_id } => {
+                    self.internal_accept_swap(
+                        swap_id,
+                        nft_contract_id,
+                        token_id,
+                        owner_id,
+                        approval_id,
+                    );
+                }
+            }
+            return;
+        }
+
+        //we need to enforce that the user has enough storage for 1 EXTRA sale.
+        self.internal_assert_storage(&signer_id, 1);
 
         //if all these checks pass we can create the sale conditions object.
-        let SaleArgs { sale_conditions } =
+        let args: SaleArgs =
             //the sale conditions come from the msg field. The market assumes that the user passed
             //in a proper msg. If they didn't, it panics. 
             near_sdk::serde_json::from_str(&msg).expect("Not valid SaleArgs");
+        //make sure the market accepts the nft contract for the listing
+        self.assert_collection_allows(&nft_contract_id, args.listing_type());
+        let expires_at = args.expires_at;
+        let reserved_for = args.reserved_for.clone();
+        assert_reserved_for(&reserved_for, &owner_id);
+        let (sale_conditions, dutch_auction) = args.into_listing();
+        //make sure the market accepts every currency the sale is priced in
+        self.assert_sale_conditions_supported(&sale_conditions);
+
+        //insert the sale and populate the collections necessary for the view calls
+        self.internal_add_sale(Sale {
+            owner_id,                                     //owner of the sale / token
+            approval_id, //approval ID for that token that was given to the market
+            nft_contract_id: nft_contract_id.to_string(), //NFT contract the token was minted on
+            token_id,    //the actual token ID
+            sale_conditions, //the sale conditions
+            escrowed: false, //the token stays with the owner until the sale goes through
+            expires_at: sale_expires_at(expires_at), //when the sale can no longer be purchased
+            dutch_auction, //the dutch auction configuration (if any)
+            reserved_for, //the only account that can purchase the sale (if any)
+        });
+    }
 
-        //create the unique sale ID which is the contract + DELIMITER + token ID
-        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
-
-        //insert the key value pair into the sales map. Key is the unique ID. value is the sale object
-        self.sales.insert(
-            &contract_and_token_id,
-            &Sale {
-                owner_id: owner_id.clone(),                   //owner of the sale / token
-                approval_id, //approval ID for that token that was given to the market
-                nft_contract_id: nft_contract_id.to_string(), //NFT contract the token was minted on
-                token_id: token_id.clone(), //the actual token ID
-                sale_conditions, //the sale conditions
-            },
+    /// where we add a sale for every token approved through nft_batch_approve
+    fn nft_on_batch_approve(
+        &mut self,
+        approvals: Vec<(TokenId, u64)>,
+        owner_id: AccountId,
+        msg: String,
+    ) {
+        // get the contract ID which is the predecessor
+        let nft_contract_id = env::predecessor_account_id();
+        //get the signer which is the person who initiated the transaction
+        let signer_id = env::signer_account_id();
+
+        //make sure that the signer isn't the predecessor. This is so that we're sure
+        //this was called via a cross-contract call
+        assert_ne!(
+            nft_contract_id, signer_id,
+            "nft_on_batch_approve should only be called via cross-contract call"
         );
+        //make sure the owner ID is the signer.
+        assert_eq!(owner_id, signer_id, "owner_id should be signer_id");
 
-        //Extra functionality that populates collections necessary for the view calls
+        //we need to enforce that the user has enough storage for all the EXTRA sales at once.
+        self.internal_assert_storage(&signer_id, approvals.len() as u64);
 
-        //get the sales by owner ID for the given owner. If there are none, we create a new empty set
-        let mut by_owner_id = self.by_owner_id.get(&owner_id).unwrap_or_else(|| {
-            UnorderedSet::new(
-                StorageKey::ByOwnerIdInner {
-                    //we get a new unique prefix for the collection by hashing the owner
-                    account_id_hash: hash_account_id(&owner_id),
-                }
-                .try_to_vec()
-                .unwrap(),
-            )
-        });
+        //every token in the batch is listed with the same sale conditions
+        let args: SaleArgs = near_sdk::serde_json::from_str(&msg).expect("Not valid SaleArgs");
+        //make sure the market accepts the nft contract for the listing
+        self.assert_collection_allows(&nft_contract_id, args.listing_type());
+        let expires_at = sale_expires_at(args.expires_at);
+        let reserved_for = args.reserved_for.clone();
+        assert_reserved_for(&reserved_for, &owner_id);
+        let (sale_conditions, dutch_auction) = args.into_listing();
+        self.assert_sale_conditions_supported(&sale_conditions);
 
-        //insert the unique sale ID into the set
-        by_owner_id.insert(&contract_and_token_id);
-        //insert that set back into the collection for the owner
-        self.by_owner_id.insert(&owner_id, &by_owner_id);
-
-        //get the token IDs for the given nft contract ID. If there are none, we create a new empty set
-        let mut by_nft_contract_id = self
-            .by_nft_contract_id
-            .get(&nft_contract_id)
-            .unwrap_or_else(|| {
-                UnorderedSet::new(
-                    StorageKey::ByNFTContractIdInner {
-                        //we get a new unique prefix for the collection by hashing the owner
-                        account_id_hash: hash_account_id(&nft_contract_id),
-                    }
-                    .try_to_vec()
-                    .unwrap(),
-                )
+        for (token_id, approval_id) in approvals {
+            self.internal_add_sale(Sale {
+                owner_id: owner_id.clone(),
+                approval_id,
+                nft_contract_id: nft_contract_id.to_string(),
+                token_id,
+                sale_conditions: sale_conditions.clone(),
+                escrowed: false,
+                expires_at,
+                dutch_auction: dutch_auction.clone(),
+                reserved_for: reserved_for.clone(),
             });
+        }
+    }
+}
 
-        //insert the token ID into the set
-        by_nft_contract_id.insert(&token_id);
-        //insert the set back into the collection for the given nft contract ID
-        self.by_nft_contract_id
-            .insert(&nft_contract_id, &by_nft_contract_id);
+#[near_bindgen]
+impl NonFungibleTokenReceiver for Contract {
+    /// where we add an escrowed sale or auction. Returns true if the token should be returned to the owner
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        // get the contract ID which is the predecessor
+        let nft_contract_id = env::predecessor_account_id();
+        //get the signer which is the person who initiated the transaction
+        let signer_id = env::signer_account_id();
+
+        //make sure that the signer isn't the predecessor. This is so that we're sure
+        //this was called via a cross-contract call
+        assert_ne!(
+            nft_contract_id, signer_id,
+            "nft_on_transfer should only be called via cross-contract call"
+        );
+        //make sure the previous owner is the signer (the token may be sent by an approved account on their behalf)
+        assert_eq!(
+            previous_owner_id, signer_id,
+            "previous_owner_id should be signer_id"
+        );
+
+        //if the listing parameters can't be parsed, we return the token to the owner
+        let args: EscrowArgs = match near_sdk::serde_json::from_str(&msg) {
+            Ok(args) => args,
+            Err(_) => {
+                env::log_str(&format!(
+                    "Not valid EscrowArgs from {}, returning token",
+                    sender_id
+                ));
+                return PromiseOrValue::Value(true);
+            }
+        };
+
+        //make sure the market accepts the nft contract for the listing
+        let listing_type = match (args.start_time, args.end_time) {
+            (Some(_), Some(_)) => ListingType::Auction,
+            _ => ListingType::Sale,
+        };
+        self.assert_collection_allows(&nft_contract_id, listing_type);
+
+        //we need to enforce that the user has enough storage for 1 EXTRA listing.
+        self.internal_assert_storage(&previous_owner_id, 1);
+        //make sure the market accepts every currency the listing is priced in
+        let sale_conditions = args.sale_conditions.into_sale_conditions();
+        self.assert_sale_conditions_supported(&sale_conditions);
+
+        match (args.start_time, args.end_time) {
+            //both times were passed in so we list the token as an auction
+            (Some(start_time), Some(end_time)) => {
+                assert!(end_time > start_time, "Auction must end after it starts");
+                //auctions are open to every bidder
+                assert!(
+                    args.reserved_for.is_none(),
+                    "Auctions cannot be reserved for an account"
+                );
+                //auctions are always in NEAR so other currencies can't be passed in
+                assert!(
+                    sale_conditions.len() == 1,
+                    "Auctions can only be priced in NEAR"
+                );
+                self.internal_add_auction(Auction {
+                    owner_id: previous_owner_id,
+                    //the market owns the token so there is no auction approval
+                    auction_id: 0,
+                    nft_contract_id: nft_contract_id.to_string(),
+                    auction_token: token_id,
+                    //auctions are always in NEAR
+                    sale_conditions: *sale_conditions
+                        .get(&near_currency())
+                        .expect("Auctions must have a price in NEAR"),
+                    bid_escrow: U128(0),
+                    start_time: (start_time as u128) * 1_000_000_000,
+                    end_time: (end_time as u128) * 1_000_000_000,
+                    winner: None,
+                    is_near_claimed: false,
+                    is_nft_claimed: false,
+                    proceeds: None,
+                    escrowed: true,
+                    min_bid_increment: args.auction_args.min_bid_increment,
+                    reserve_price: args.auction_args.reserve_price,
+                    extension: args
+                        .auction_args
+                        .extension
+                        .map(|extension| extension.into_auction_extension(end_time)),
+                    extensions: vec![],
+                    no_bid_fallback: args
+                        .auction_args
+                        .no_bid_fallback
+                        .map(|no_bid_fallback| no_bid_fallback.into_nanoseconds(true)),
+                    sealed_bid: args
+                        .auction_args
+                        .sealed_bid
+                        .map(|sealed_bid| sealed_bid.into_sealed_bid_config(end_time)),
+                    settlement: args
+                        .auction_args
+                        .settlement
+                        .unwrap_or(AuctionSettlement::HighestBid),
+                    second_bid: None,
+                    buy_now_price: auction_buy_now_price(
+                        *sale_conditions.get(&near_currency()).unwrap(),
+                        args.auction_args.buy_now_price,
+                    ),
+                });
+            }
+            (None, None) => {
+                assert_reserved_for(&args.reserved_for, &previous_owner_id);
+                self.internal_add_sale(Sale {
+                    owner_id: previous_owner_id,
+                    //the market owns the token so there is no approval
+                    approval_id: 0,
+                    nft_contract_id: nft_contract_id.to_string(),
+                    token_id,
+                    sale_conditions,
+                    escrowed: true,
+                    expires_at: sale_expires_at(args.expires_at),
+                    dutch_auction: None,
+                    reserved_for: args.reserved_for,
+                });
+            }
+            _ => {
+                env::log_str("Auctions require both a start_time and an end_time, returning token");
+                return PromiseOrValue::Value(true);
+            }
+        }
+
+        //keep the token
+        PromiseOrValue::Value(false)
     }
 }
+
+//makes sure a private sale isn't reserved for the owner of the token
+fn assert_reserved_for(reserved_for: &Option<AccountId>, owner_id: &AccountId) {
+    assert_ne!(
+        reserved_for.as_ref(),
+        Some(owner_id),
+        "A sale cannot be reserved for its owner"
+    );
+}
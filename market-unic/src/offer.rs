@@ -0,0 +1,183 @@
+use crate::*;
+
+//struct that holds important information about each offer made on a token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Offer {
+    //account that made the offer and will receive the token
+    pub buyer_id: AccountId,
+    //nft contract where the token was minted
+    pub nft_contract_id: String,
+    //actual token ID the offer is for
+    pub token_id: String,
+    //price in yoctoNEAR that the buyer escrowed with the market
+    pub price: SalePriceInYoctoNear,
+    //time (in nanoseconds) after which the offer can no longer be accepted
+    pub expires_at: Option<u64>,
+}
+
+#[near_bindgen]
+impl Contract {
+    //make an offer on any token (listed or not). The attached deposit is held by the market until
+    //the offer is accepted, cancelled or expires. Making a new offer on the same token replaces the old one.
+    #[payable]
+    pub fn make_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        expires_at: Option<u64>,
+    ) {
+        //get the attached deposit and make sure it's greater than 0
+        let deposit = env::attached_deposit();
+        assert!(deposit > 0, "Attached deposit must be greater than 0");
+
+        //the expiry is passed in seconds and stored in nanoseconds
+        let expires_at = expires_at.map(|expires_at| expires_at * 1_000_000_000);
+        if let Some(expires_at) = expires_at {
+            assert!(
+                expires_at > env::block_timestamp(),
+                "Offer must expire in the future"
+            );
+        }
+
+        let buyer_id = env::predecessor_account_id();
+
+        //if the buyer already has an offer on this token, we refund it and replace it.
+        //Otherwise we need to enforce that the buyer has enough storage for 1 EXTRA offer.
+        let offer_id = offer_id(&nft_contract_id, &token_id, &buyer_id);
+        if let Some(previous_offer) = self.offers.get(&offer_id) {
+            self.internal_release_escrow(previous_offer.price.0);
+            Promise::new(previous_offer.buyer_id).transfer(previous_offer.price.0);
+        } else {
+            self.internal_assert_storage(&buyer_id, 1);
+        }
+        self.internal_lock_escrow(deposit);
+
+        self.internal_add_offer(Offer {
+            buyer_id,
+            nft_contract_id: nft_contract_id.to_string(),
+            token_id,
+            price: U128(deposit),
+            expires_at,
+        });
+    }
+
+    //cancels an offer and refunds the escrowed deposit to the buyer
+    #[payable]
+    pub fn cancel_offer(&mut self, nft_contract_id: AccountId, token_id: String) {
+        //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
+        assert_one_yocto();
+
+        let buyer_id = env::predecessor_account_id();
+        let offer = self.internal_remove_offer(&offer_id(&nft_contract_id, &token_id, &buyer_id));
+
+        self.internal_release_escrow(offer.price.0);
+        Promise::new(buyer_id).transfer(offer.price.0);
+    }
+
+    //removes an expired offer and refunds the escrowed deposit to the buyer. Anyone can call this.
+    pub fn remove_expired_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        buyer_id: AccountId,
+    ) {
+        let offer_id = offer_id(&nft_contract_id, &token_id, &buyer_id);
+        let offer = self.offers.get(&offer_id).expect("No offer");
+        assert!(offer_is_expired(&offer), "Offer has not expired yet");
+
+        let offer = self.internal_remove_offer(&offer_id);
+        self.internal_release_escrow(offer.price.0);
+        Promise::new(offer.buyer_id).transfer(offer.price.0);
+    }
+
+    //private method used to resolve the promise when calling nft_transfer_payout for an accepted offer.
+    //the escrowed price is released here since resolve_purchase either pays it out or refunds it to the buyer
+    #[private]
+    pub fn resolve_offer_purchase(&mut self, purchase: Purchase) -> U128 {
+        self.internal_release_escrow(purchase.price.0);
+        self.resolve_purchase(purchase)
+    }
+}
+
+impl Contract {
+    //internal method used when the token owner accepts an offer by approving the market.
+    //this will remove the offer, transfer and get the payout from the nft contract, and then distribute royalties
+    pub(crate) fn internal_accept_offer(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+        buyer_id: AccountId,
+    ) -> Promise {
+        let offer_id = offer_id(&nft_contract_id, &token_id, &buyer_id);
+        let offer = self.offers.get(&offer_id).expect("No offer");
+
+        assert!(!offer_is_expired(&offer), "Offer has expired");
+        assert_ne!(owner_id, buyer_id, "Cannot accept your own offer.");
+
+        //get the offer object by removing the offer. The escrowed price stays locked until resolve_offer_purchase
+        //either pays it out or refunds it
+        let offer = self.internal_remove_offer(&offer_id);
+        //a listing of the token would be left behind with a consumed approval so it's taken down
+        self.internal_remove_stale_sale(&nft_contract_id, &token_id);
+
+        //initiate a cross contract call to the nft contract. This will transfer the token to the buyer and return
+        //a payout object used for the market to distribute the escrowed deposit to the appropriate accounts.
+        ext_contract::nft_transfer_payout(
+            buyer_id.clone(),
+            token_id.clone(),
+            approval_id,
+            "payout from market".to_string(),
+            offer.price,
+            10,
+            nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        //resolve purchase will pay the accounts or refund the buyer if something went wrong
+        .then(ext_self::resolve_offer_purchase(
+            Purchase {
+                buyer_id,
+                seller_id: owner_id,
+                price: offer.price,
+                nft_contract_id,
+                token_id,
+                //the token stays with its owner until it's transferred to the buyer
+                escrowed: false,
+            },
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_PURCHASE,
+        ))
+    }
+}
+
+//returns the unique offer ID. The parts are hashed as a borsh encoded tuple (rather than joined with the
+//DELIMITER, which can be part of account and token IDs) so two different offers can never share an ID
+pub(crate) fn offer_id(
+    nft_contract_id: &AccountId,
+    token_id: &str,
+    buyer_id: &AccountId,
+) -> OfferId {
+    let mut hash = CryptoHash::default();
+    hash.copy_from_slice(&env::sha256(
+        &(nft_contract_id, token_id, buyer_id).try_to_vec().unwrap(),
+    ));
+    hash
+}
+
+//checks if an offer can no longer be accepted
+pub(crate) fn offer_is_expired(offer: &Offer) -> bool {
+    offer
+        .expires_at
+        .map(|expires_at| env::block_timestamp() >= expires_at)
+        .unwrap_or(false)
+}
+
+//this is the cross contract call that we call on our own contract.
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn resolve_offer_purchase(&mut self, purchase: Purchase) -> U128;
+}
@@ -0,0 +1,92 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// views
+    //returns the number of offers the marketplace holds (as a string)
+    pub fn get_supply_offers(&self) -> U64 {
+        //returns the offers object length wrapped as a U64
+        U64(self.offers.len())
+    }
+
+    //returns paginated offer objects made on a given token. (result is a vector of offers)
+    pub fn get_offers_by_token(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Offer> {
+        //get the set of buyers with an offer on the given token
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let offers_by_token =
+            if let Some(offers_by_token) = self.offers_by_token.get(&contract_and_token_id) {
+                offers_by_token
+            } else {
+                return vec![];
+            };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the buyers
+        offers_by_token
+            .as_vector()
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //we'll map the buyers into Offer objects by passing in the unique offer ID
+            .map(|buyer_id| {
+                self.offers
+                    .get(&offer_id(&nft_contract_id, &token_id, &buyer_id))
+                    .unwrap()
+            })
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //returns paginated offer objects made by a given buyer. (result is a vector of offers)
+    pub fn get_offers_by_buyer_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Offer> {
+        //get the set of offer IDs for the given buyer
+        let offers_by_buyer_id =
+            if let Some(offers_by_buyer_id) = self.offers_by_buyer_id.get(&account_id) {
+                offers_by_buyer_id
+            } else {
+                return vec![];
+            };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the offer IDs
+        offers_by_buyer_id
+            .as_vector()
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //we'll map the offer IDs into Offer objects
+            .map(|offer_id| self.offers.get(&offer_id).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //get the offer a buyer made on a given token
+    pub fn get_offer(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        buyer_id: AccountId,
+    ) -> Option<Offer> {
+        self.offers
+            .get(&offer_id(&nft_contract_id, &token_id, &buyer_id))
+    }
+}
@@ -1,5 +1,4 @@
 use crate::*;
-use near_sdk::promise_result_as_success;
 
 //struct that holds important information about each sale on the market
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
@@ -13,8 +12,31 @@ pub struct Sale {
     pub nft_contract_id: String,
     //actual token ID for sale
     pub token_id: String,
-    //sale price in yoctoNEAR that the token is listed for
-    pub sale_conditions: SalePriceInYoctoNear,
+    //sale prices that the token is listed for, per currency (NEAR or an FT contract ID)
+    pub sale_conditions: SaleConditions,
+    //whether the market holds the token in custody (listed through nft_transfer_call)
+    pub escrowed: bool,
+    //time (in nanoseconds) after which the sale can no longer be purchased
+    pub expires_at: Option<u64>,
+    //if the sale is a dutch auction, the configuration used to compute its current price in NEAR
+    pub dutch_auction: Option<DutchAuction>,
+    //if set, the only account that can purchase the sale (private sale)
+    pub reserved_for: Option<AccountId>,
+}
+
+//struct for keeping track of a purchase paid in NEAR while the nft contract transfers the token
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Purchase {
+    pub buyer_id: AccountId,
+    pub seller_id: AccountId,
+    //price in yoctoNEAR the token was purchased for
+    pub price: U128,
+    //nft contract where the token was minted
+    pub nft_contract_id: AccountId,
+    pub token_id: String,
+    //whether the market holds the token in custody
+    pub escrowed: bool,
 }
 
 #[near_bindgen]
@@ -25,16 +47,41 @@ impl Contract {
         //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
         assert_one_yocto();
         //get the sale object as the return value from removing the sale internally
-        let sale = self.internal_remove_sale(nft_contract_id.into(), token_id);
+        let sale = self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
         //get the predecessor of the call and make sure they're the owner of the sale
         let owner_id = env::predecessor_account_id();
         //if this fails, the remove sale will revert
         assert_eq!(owner_id, sale.owner_id, "Must be sale owner");
+
+        //if the market was holding the token, we give it back to the owner
+        if sale.escrowed {
+            self.internal_return_escrowed_token(nft_contract_id, token_id, owner_id);
+        }
+    }
+
+    //removes an expired sale from the market and releases the storage the owner was paying for it. Anyone can call this.
+    pub fn remove_expired_sale(&mut self, nft_contract_id: AccountId, token_id: String) {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let sale = self.sales.get(&contract_and_token_id).expect("No sale");
+        assert!(sale_is_expired(&sale), "Sale has not expired yet");
+
+        let sale = self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+
+        //if the market was holding the token, we give it back to the owner
+        if sale.escrowed {
+            self.internal_return_escrowed_token(nft_contract_id, token_id, sale.owner_id);
+        }
     }
 
-    //updates the price for a sale on the market
+    //updates the price for a sale on the market in the given currency (NEAR if no FT contract ID is passed in)
     #[payable]
-    pub fn update_price(&mut self, nft_contract_id: AccountId, token_id: String, price: U128) {
+    pub fn update_price(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        price: U128,
+        ft_token_id: Option<FungibleTokenId>,
+    ) {
         //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
         assert_one_yocto();
 
@@ -52,8 +99,18 @@ impl Contract {
             "Must be sale owner"
         );
 
-        //set the sale conditions equal to the passed in price
-        sale.sale_conditions = price;
+        //the price of a dutch auction is driven by its configuration
+        assert!(
+            sale.dutch_auction.is_none(),
+            "Cannot update the price of a dutch auction"
+        );
+
+        //make sure the currency is accepted by the market
+        let currency = currency_for(ft_token_id);
+        self.assert_currency_supported(&currency);
+
+        //set the sale conditions for the currency equal to the passed in price
+        sale.sale_conditions.insert(currency, price);
         //insert the sale back into the map for the unique sale ID
         self.sales.insert(&contract_and_token_id, &sale);
     }
@@ -76,9 +133,13 @@ impl Contract {
         //get the buyer ID which is the person who called the function and make sure they're not the owner of the sale
         let buyer_id = env::predecessor_account_id();
         assert_ne!(sale.owner_id, buyer_id, "Cannot bid on your own sale.");
+        //make sure the listing hasn't expired
+        assert!(!sale_is_expired(&sale), "Sale has expired");
+        //make sure the buyer is allowed to purchase a private sale
+        assert_sale_reserved_for(&sale, &buyer_id);
 
-        //get the u128 price of the token (dot 0 converts from U128 to u128)
-        let price = sale.sale_conditions.0;
+        //get the current u128 price of the token in NEAR (for dutch auctions, this depends on the block timestamp)
+        let price = sale_price_in_near(&sale).expect("Sale is not priced in NEAR");
 
         //make sure the deposit is greater than the price
         assert!(
@@ -87,8 +148,23 @@ impl Contract {
             price
         );
 
+        //the first buyer to cover the price of a dutch auction wins it at that price and gets the excess back.
+        //fixed price sales keep paying the whole deposit to the seller
+        let price = if let Some(dutch_auction) = &sale.dutch_auction {
+            assert!(
+                env::block_timestamp() >= dutch_auction.start_time,
+                "Dutch auction has not started yet"
+            );
+            if deposit > price {
+                Promise::new(buyer_id.clone()).transfer(deposit - price);
+            }
+            price
+        } else {
+            deposit
+        };
+
         //process the purchase (which will remove the sale, transfer and get the payout from the nft contract, and then distribute royalties)
-        self.process_purchase(contract_id, token_id, U128(deposit), buyer_id);
+        self.process_purchase(contract_id, token_id, U128(price), buyer_id);
     }
 
     //private function used when a sale is purchased.
@@ -108,7 +184,7 @@ impl Contract {
         //a payout object used for the market to distribute funds to the appropriate accounts.
         ext_contract::nft_transfer_payout(
             buyer_id.clone(),                 //purchaser (person to transfer the NFT to)
-            token_id,                         //token ID to transfer
+            token_id.clone(),                 //token ID to transfer
             sale.approval_id, //market contract's approval ID in order to transfer the token on behalf of the owner
             "payout from market".to_string(), //memo (to include some context)
             /*
@@ -117,7 +193,7 @@ impl Contract {
             */
             price,
             10,
-            nft_contract_id,
+            nft_contract_id.clone(),
             1,
             GAS_FOR_NFT_TRANSFER,
             //the maximum amount of accounts the market can payout at once (this is limited by GAS)
@@ -125,8 +201,14 @@ impl Contract {
         //after the transfer payout has been initiated, we resolve the promise by calling our own resolve_purchase function.
         //resolve purchase will take the payout object returned from the nft_transfer_payout and actually pay the accounts
         .then(ext_self::resolve_purchase(
-            buyer_id,
-            price,
+            Purchase {
+                buyer_id,
+                seller_id: sale.owner_id,
+                price,
+                nft_contract_id,
+                token_id,
+                escrowed: sale.escrowed,
+            },
             env::current_account_id(),
             NO_DEPOSIT,
             GAS_FOR_RESOLVE_PURCHASE,
@@ -136,46 +218,21 @@ impl Contract {
     /*
         private method used to resolve the promise when calling nft_transfer_payout. This will take the payout object and
         check to see if it's authentic and there's no problems. If everything is fine, it will pay the accounts. If there's a problem,
-        it will refund the buyer for the price.
+        it will refund the buyer for the price (and give a token held in escrow back to the seller).
     */
     #[private]
-    pub fn resolve_purchase(&mut self, buyer_id: AccountId, price: U128) -> U128 {
+    pub fn resolve_purchase(&mut self, purchase: Purchase) -> U128 {
+        let Purchase {
+            buyer_id,
+            seller_id,
+            price,
+            nft_contract_id,
+            token_id,
+            escrowed,
+        } = purchase;
+
         // checking for payout information returned from the nft_transfer_payout method
-        let payout_option = promise_result_as_success().and_then(|value| {
-            //if we set the payout_option to None, that means something went wrong and we should refund the buyer
-            near_sdk::serde_json::from_slice::<Payout>(&value)
-                //converts the result to an optional value
-                .ok()
-                //returns None if the none. Otherwise executes the following logic
-                .and_then(|payout_object| {
-                    //we'll check if length of the payout object is > 10 or it's empty. In either case, we return None
-                    if payout_object.payout.len() > 10 || payout_object.payout.is_empty() {
-                        env::log_str("Cannot have more than 10 royalties");
-                        None
-
-                    //if the payout object is the correct length, we move forward
-                    } else {
-                        //we'll keep track of how much the nft contract wants us to payout. Starting at the full price payed by the buyer
-                        let mut remainder = price.0;
-
-                        //loop through the payout and subtract the values from the remainder.
-                        for &value in payout_object.payout.values() {
-                            //checked sub checks for overflow or any errors and returns None if there are problems
-                            remainder = remainder.checked_sub(value.0)?;
-                        }
-                        //Check to see if the NFT contract sent back a faulty payout that requires us to pay more or too little.
-                        //The remainder will be 0 if the payout summed to the total price. The remainder will be 1 if the royalties
-                        //we something like 3333 + 3333 + 3333.
-                        if remainder == 0 || remainder == 1 {
-                            //set the payout_option to be the payout because nothing went wrong
-                            Some(payout_object.payout)
-                        } else {
-                            //if the remainder was anything but 1 or 0, we return None
-                            None
-                        }
-                    }
-                })
-        });
+        let payout_option = verify_payout(price, &seller_id);
 
         // if the payout option was some payout, we set this payout variable equal to that some payout
         let payout = if let Some(payout_option) = payout_option {
@@ -183,10 +240,18 @@ impl Contract {
         //if the payout option was None, we refund the buyer for the price they payed and return
         } else {
             Promise::new(buyer_id).transfer(u128::from(price));
+            //the market still holds escrowed tokens so we give them back to the seller
+            if escrowed {
+                self.internal_return_escrowed_token(nft_contract_id, token_id, seller_id);
+            }
             // leave function and return the price that was refunded
             return price;
         };
 
+        //take the market commission before the royalties are distributed
+        let payout =
+            self.internal_take_market_fee(&nft_contract_id, &near_currency(), price, payout);
+
         // NEAR payouts
         for (receiver_id, amount) in payout {
             Promise::new(receiver_id).transfer(amount.0);
@@ -197,6 +262,35 @@ impl Contract {
     }
 }
 
+//converts the expiry passed in a listing msg (in seconds) to nanoseconds and makes sure it's in the future
+pub(crate) fn sale_expires_at(expires_at: Option<u64>) -> Option<u64> {
+    let expires_at = expires_at.map(|expires_at| expires_at * 1_000_000_000);
+    if let Some(expires_at) = expires_at {
+        assert!(
+            expires_at > env::block_timestamp(),
+            "Sale must expire in the future"
+        );
+    }
+    expires_at
+}
+
+//checks if a sale can no longer be purchased
+pub(crate) fn sale_is_expired(sale: &Sale) -> bool {
+    sale.expires_at
+        .map(|expires_at| env::block_timestamp() >= expires_at)
+        .unwrap_or(false)
+}
+
+//makes sure a private sale is only purchased by the account it's reserved for
+pub(crate) fn assert_sale_reserved_for(sale: &Sale, buyer_id: &AccountId) {
+    if let Some(reserved_for) = &sale.reserved_for {
+        assert_eq!(
+            reserved_for, buyer_id,
+            "Sale is reserved for another account"
+        );
+    }
+}
+
 //this is the cross contract call that we call on our own contract.
 /*
     private method used to resolve the promise when calling nft_transfer_payout. This will take the payout object and
@@ -205,5 +299,5 @@ impl Contract {
 */
 #[ext_contract(ext_self)]
 trait ExtSelf {
-    fn resolve_purchase(&mut self, buyer_id: AccountId, price: U128) -> Promise;
+    fn resolve_purchase(&mut self, purchase: Purchase) -> Promise;
 }
@@ -1,5 +1,23 @@
 use crate::*;
 
+//checks if a sale can be paid in the given currency. If no currency is given, every sale matches
+fn sale_accepts_currency(sale: &Sale, currency: &Option<Currency>) -> bool {
+    currency
+        .as_ref()
+        .map(|currency| sale.sale_conditions.contains_key(currency))
+        .unwrap_or(true)
+}
+
+//checks if a sale should be returned by a view. Expired sales are only returned if they're asked for
+fn sale_is_listed(sale: &Sale, include_expired: Option<bool>) -> bool {
+    include_expired.unwrap_or(false) || !sale_is_expired(sale)
+}
+
+//checks if a sale should be returned by a public market view. Private sales are never returned
+fn sale_is_visible(sale: &Sale, include_expired: Option<bool>) -> bool {
+    sale.reserved_for.is_none() && sale_is_listed(sale, include_expired)
+}
+
 #[near_bindgen]
 impl Contract {
     /// views
@@ -29,11 +47,15 @@ impl Contract {
     }
 
     //returns paginated sale objects for a given account. (result is a vector of sales)
+    //if a currency is passed in, only the sales that accept that currency are returned
+    //expired sales are left out unless include_expired is true
     pub fn get_sales_by_owner_id(
         &self,
         account_id: AccountId,
         from_index: Option<U128>,
         limit: Option<u64>,
+        currency: Option<Currency>,
+        include_expired: Option<bool>,
     ) -> Vec<Sale> {
         //get the set of token IDs for sale for the given account ID
         let by_owner_id = self.by_owner_id.get(&account_id);
@@ -52,12 +74,17 @@ impl Contract {
         
         //iterate through the keys vector
         keys.iter()
+            //we'll map the token IDs which are strings into Sale objects
+            .map(|token_id| self.sales.get(&token_id).unwrap())
+            //only keep the sales that accept the passed in currency (if any)
+            .filter(|sale| sale_accepts_currency(sale, &currency))
+            //only keep the sales that can still be purchased (unless expired sales were asked for). The owner's
+            //private sales are included
+            .filter(|sale| sale_is_listed(sale, include_expired))
             //skip to the index we specified in the start variable
             .skip(start as usize) 
             //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
             .take(limit.unwrap_or(0) as usize) 
-            //we'll map the token IDs which are strings into Sale objects
-            .map(|token_id| self.sales.get(&token_id).unwrap())
             //since we turned the keys into an iterator, we need to turn it back into a vector to return
             .collect()
     }
@@ -79,11 +106,15 @@ impl Contract {
     }
 
     //returns paginated sale objects associated with a given nft contract. (result is a vector of sales)
+    //if a currency is passed in, only the sales that accept that currency are returned
+    //expired sales are left out unless include_expired is true
     pub fn get_sales_by_nft_contract_id(
         &self,
         nft_contract_id: AccountId,
         from_index: Option<U128>,
         limit: Option<u64>,
+        currency: Option<Currency>,
+        include_expired: Option<bool>,
     ) -> Vec<Sale> {
         //get the set of token IDs for sale for the given contract ID
         let by_nft_contract_id = self.by_nft_contract_id.get(&nft_contract_id);
@@ -103,12 +134,48 @@ impl Contract {
         
         //iterate through the keys vector
         keys.iter()
+            //we'll map the token IDs which are strings into Sale objects by passing in the unique sale ID (contract + DELIMITER + token ID)
+            .map(|token_id| self.sales.get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id)).unwrap())
+            //only keep the sales that accept the passed in currency (if any)
+            .filter(|sale| sale_accepts_currency(sale, &currency))
+            //only keep the public sales that can still be purchased (unless expired sales were asked for)
+            .filter(|sale| sale_is_visible(sale, include_expired))
+            //skip to the index we specified in the start variable
+            .skip(start as usize) 
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize) 
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //returns paginated private sales reserved for a given buyer. (result is a vector of sales)
+    //expired sales are left out unless include_expired is true
+    pub fn get_private_sales_by_buyer_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+        include_expired: Option<bool>,
+    ) -> Vec<Sale> {
+        //get the set of unique sale IDs reserved for the given buyer
+        let private_sales = if let Some(private_sales) = self.private_sales_by_buyer_id.get(&account_id) {
+            private_sales
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        private_sales.as_vector().iter()
+            //we'll map the unique sale IDs into Sale objects
+            .map(|contract_and_token_id| self.sales.get(&contract_and_token_id).unwrap())
+            //only keep the sales that can still be purchased (unless expired sales were asked for)
+            .filter(|sale| include_expired.unwrap_or(false) || !sale_is_expired(sale))
             //skip to the index we specified in the start variable
             .skip(start as usize) 
             //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
             .take(limit.unwrap_or(0) as usize) 
-            //we'll map the token IDs which are strings into Sale objects by passing in the unique sale ID (contract + DELIMITER + token ID)
-            .map(|token_id| self.sales.get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id)).unwrap())
             //since we turned the keys into an iterator, we need to turn it back into a vector to return
             .collect()
     }
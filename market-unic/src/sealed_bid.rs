@@ -0,0 +1,322 @@
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+
+//what happens to the deposits of sealed bids that were never revealed
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum UnrevealedBidRule {
+    //the deposit goes to the seller
+    Forfeit,
+    //the deposit goes back to the bidder
+    Refund,
+}
+
+//struct that holds the configuration of a sealed-bid auction. Bids are committed between the start and
+//end time of the auction and revealed between the end time and the reveal deadline (in nanoseconds)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SealedBidConfig {
+    pub reveal_end_time: u64,
+    pub unrevealed_bids: UnrevealedBidRule,
+}
+
+//struct for the sealed-bid configuration passed in the listing msg. The reveal deadline is in seconds
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SealedBidArgs {
+    pub reveal_end_time: u64,
+    pub unrevealed_bids: UnrevealedBidRule,
+}
+
+//struct that holds a committed bid until it is revealed
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SealedBid {
+    //sha256 hash of "<nft contract>.<token ID>:<bidder>:<amount in yoctoNEAR>:<salt>". The auction and
+    //the bidder are part of it so a commitment can't be copied by another bidder or onto another auction
+    pub bid_hash: Base64VecU8,
+    //deposit in yoctoNEAR that covers the bid
+    pub deposit: U128,
+}
+
+impl SealedBidArgs {
+    //validates the passed in configuration and converts the reveal deadline to nanoseconds.
+    //the auction has to be settled after the reveal phase, while the auction approval is still valid
+    pub fn into_sealed_bid_config(self, end_time: u64) -> SealedBidConfig {
+        assert!(
+            self.reveal_end_time > end_time,
+            "Reveal deadline must be after the end time"
+        );
+        assert!(
+            self.reveal_end_time * 1_000_000_000
+                <= end_time * 1_000_000_000 + AUCTION_SETTLEMENT_PERIOD,
+            "Reveal deadline cannot be after the settlement period of the auction"
+        );
+
+        SealedBidConfig {
+            reveal_end_time: self.reveal_end_time * 1_000_000_000,
+            unrevealed_bids: self.unrevealed_bids,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    //commits a sealed bid on an auction. The attached deposit must cover the bid and is held by the market.
+    //a bidder can only commit once per auction
+    #[payable]
+    pub fn commit_sealed_bid(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        bid_hash: Base64VecU8,
+    ) {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        assert!(auction.sealed_bid.is_some(), "Auction is not sealed-bid");
+        let now = env::block_timestamp();
+        assert!(
+            now > auction.start_time as u64,
+            "This auction has not started"
+        );
+        assert!(now < auction.end_time as u64, "The commit phase is over");
+
+        let bidder_id = env::predecessor_account_id();
+        assert_ne!(
+            auction.owner_id, bidder_id,
+            "Cannot bid on your own auction."
+        );
+        let deposit = env::attached_deposit();
+        assert!(deposit > 0, "Attached deposit must be greater than 0");
+        assert_eq!(bid_hash.0.len(), 32, "Bid hash must be a sha256 hash");
+
+        //get the committed bids of the auction. If there are none, we create a new empty map
+        let mut sealed_bids = self
+            .sealed_bids
+            .get(&contract_and_auction_token_id)
+            .unwrap_or_else(|| {
+                UnorderedMap::new(
+                    StorageKey::SealedBidsInner {
+                        //we get a new unique prefix for the collection by hashing the unique auction ID
+                        token_hash: hash_contract_and_token_id(&contract_and_auction_token_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+        assert!(
+            sealed_bids.get(&bidder_id).is_none(),
+            "Bid is already committed"
+        );
+
+        sealed_bids.insert(
+            &bidder_id,
+            &SealedBid {
+                bid_hash,
+                deposit: U128(deposit),
+            },
+        );
+        self.sealed_bids
+            .insert(&contract_and_auction_token_id, &sealed_bids);
+        self.internal_lock_escrow(deposit);
+    }
+
+    //reveals a committed bid. The highest valid reveal becomes the winner and only the bid amount stays
+    //escrowed for it. Outbid, invalid and losing reveals are refunded right away
+    pub fn reveal_sealed_bid(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        amount: U128,
+        salt: String,
+    ) -> bool {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        let sealed_bid_config = auction
+            .sealed_bid
+            .clone()
+            .expect("Auction is not sealed-bid");
+        let now = env::block_timestamp();
+        assert!(
+            now >= auction.end_time as u64,
+            "The reveal phase has not started"
+        );
+        assert!(
+            now < sealed_bid_config.reveal_end_time,
+            "The reveal phase is over"
+        );
+
+        let bidder_id = env::predecessor_account_id();
+        let mut sealed_bids = self
+            .sealed_bids
+            .get(&contract_and_auction_token_id)
+            .expect("No sealed bids");
+        let sealed_bid = sealed_bids.get(&bidder_id).expect("No committed bid");
+
+        //the amount and salt must match the hash committed by the bidder on this auction
+        let bid_hash = env::sha256(
+            format!(
+                "{}:{}:{}:{}",
+                contract_and_auction_token_id, bidder_id, amount.0, salt
+            )
+            .as_bytes(),
+        );
+        assert_eq!(
+            bid_hash, sealed_bid.bid_hash.0,
+            "Bid does not match the commitment"
+        );
+
+        sealed_bids.remove(&bidder_id);
+        self.sealed_bids
+            .insert(&contract_and_auction_token_id, &sealed_bids);
+
+        //a reveal is only valid if the deposit covers it and it beats the current winning reveal.
+        //ties go to whoever revealed first
+        let is_winning = amount.0 <= sealed_bid.deposit.0 && amount.0 >= auction_min_bid(&auction);
+        if !is_winning {
+            //a covered reveal that didn't win can still be the second highest bid
+            if amount.0 <= sealed_bid.deposit.0
+                && auction
+                    .second_bid
+                    .map(|second_bid| amount.0 > second_bid.0)
+                    .unwrap_or(true)
+            {
+                auction.second_bid = Some(amount);
+                self.auctions
+                    .insert(&contract_and_auction_token_id, &auction);
+            }
+            self.internal_release_escrow(sealed_bid.deposit.0);
+            Promise::new(bidder_id).transfer(sealed_bid.deposit.0);
+            return false;
+        }
+
+        //the previous winning reveal is outbid and gets its bid back. It becomes the second highest bid
+        if let Some(old_winner_id) = auction.winner.take() {
+            self.internal_release_escrow(auction.bid_escrow.0);
+            Promise::new(old_winner_id).transfer(auction.bid_escrow.0);
+        }
+        auction.second_bid = Some(auction.sale_conditions);
+
+        //only the revealed amount stays escrowed. The rest of the deposit is refunded
+        if sealed_bid.deposit.0 > amount.0 {
+            self.internal_release_escrow(sealed_bid.deposit.0 - amount.0);
+            Promise::new(bidder_id.clone()).transfer(sealed_bid.deposit.0 - amount.0);
+        }
+        auction.winner = Some(bidder_id.clone());
+        auction.sale_conditions = amount;
+        auction.bid_escrow = amount;
+        self.auctions
+            .insert(&contract_and_auction_token_id, &auction);
+
+        //record the revealed bid in the history of the auction
+        self.internal_record_bid(Bid {
+            bidder_id,
+            nft_contract_id: auction.nft_contract_id,
+            token_id: auction.auction_token,
+            amount,
+            placed_at: now,
+        });
+
+        true
+    }
+
+    //refunds or forfeits (depending on the auction's rule) the deposits of bids that weren't revealed
+    //before the reveal deadline. Anyone can call this
+    pub fn release_unrevealed_bids(&mut self, nft_contract_id: AccountId, token_id: String) {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let auction = self
+            .auctions
+            .get(&contract_and_auction_token_id)
+            .expect("No Auction");
+
+        let sealed_bid_config = auction.sealed_bid.expect("Auction is not sealed-bid");
+        assert!(
+            env::block_timestamp() >= sealed_bid_config.reveal_end_time,
+            "The reveal phase is not over yet"
+        );
+
+        self.internal_release_sealed_bids(&contract_and_auction_token_id);
+    }
+
+    /// views
+    //returns the bid a bidder committed on a sealed-bid auction and hasn't revealed yet
+    pub fn get_sealed_bid(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        bidder_id: AccountId,
+    ) -> Option<SealedBid> {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        self.sealed_bids
+            .get(&contract_and_auction_token_id)
+            .and_then(|sealed_bids| sealed_bids.get(&bidder_id))
+    }
+
+    //returns the number of bids committed on a sealed-bid auction that weren't revealed yet
+    pub fn get_supply_sealed_bids(&self, nft_contract_id: AccountId, token_id: String) -> U64 {
+        let contract_and_auction_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        U64(self
+            .sealed_bids
+            .get(&contract_and_auction_token_id)
+            .map(|sealed_bids| sealed_bids.len())
+            .unwrap_or(0))
+    }
+}
+
+impl Contract {
+    //settles every committed bid of an auction that wasn't revealed. After the reveal deadline the auction's
+    //rule decides if the deposits are forfeited to the seller. Before it (the auction was removed early), they're refunded
+    pub(crate) fn internal_release_sealed_bids(
+        &mut self,
+        contract_and_auction_token_id: &ContractAndTokenId,
+    ) {
+        let mut sealed_bids =
+            if let Some(sealed_bids) = self.sealed_bids.remove(contract_and_auction_token_id) {
+                sealed_bids
+            } else {
+                return;
+            };
+
+        //the auction is still in the map when this is called
+        let auction = self
+            .auctions
+            .get(contract_and_auction_token_id)
+            .expect("No Auction");
+        let forfeit = auction
+            .sealed_bid
+            .map(|config| {
+                config.unrevealed_bids == UnrevealedBidRule::Forfeit
+                    && env::block_timestamp() >= config.reveal_end_time
+            })
+            .unwrap_or(false);
+
+        for (bidder_id, sealed_bid) in sealed_bids.iter() {
+            self.internal_release_escrow(sealed_bid.deposit.0);
+            let receiver_id = if forfeit {
+                auction.owner_id.clone()
+            } else {
+                bidder_id
+            };
+            Promise::new(receiver_id).transfer(sealed_bid.deposit.0);
+        }
+
+        sealed_bids.clear();
+    }
+}
+
+//returns the time (in nanoseconds) after which an auction can be settled. For sealed-bid auctions this is the reveal deadline
+pub(crate) fn auction_settles_at(auction: &Auction) -> u64 {
+    auction
+        .sealed_bid
+        .as_ref()
+        .map(|config| config.reveal_end_time)
+        .unwrap_or(auction.end_time as u64)
+}
@@ -0,0 +1,480 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+//what the maker of a swap wants in exchange for their token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum SwapTarget {
+    //one specific token
+    Token {
+        nft_contract_id: String,
+        token_id: String,
+    },
+    //any token of a collection
+    Collection {
+        nft_contract_id: String,
+    },
+}
+
+//struct that holds important information about a swap offer
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Swap {
+    //unique ID of the swap
+    pub swap_id: u64,
+    //account that gives their token
+    pub maker_id: AccountId,
+    //nft contract of the token the maker gives
+    pub nft_contract_id: String,
+    //token ID of the token the maker gives
+    pub token_id: String,
+    //market contract's approval ID for the maker's token. None until the maker approves the market for the swap
+    pub approval_id: Option<u64>,
+    //NEAR (in yoctoNEAR) the maker gives on top of their token. It is escrowed by the market
+    pub near_amount: U128,
+    //what the maker wants in exchange
+    pub wanted: SwapTarget,
+}
+
+//struct for keeping track of a swap while the market hands both tokens over
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SwapDelivery {
+    pub maker_id: AccountId,
+    pub maker_nft_contract_id: AccountId,
+    pub maker_token_id: String,
+    pub taker_id: AccountId,
+    pub taker_nft_contract_id: AccountId,
+    pub taker_token_id: String,
+    //NEAR (in yoctoNEAR) the maker gives on top of their token
+    pub near_amount: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    //creates a swap offer giving a token (plus the attached NEAR) for a token or any token of a collection.
+    //the swap becomes open once the maker approved the market for their token with the msg
+    //{"activate_swap": {"swap_id": <swap ID>}}. Returns the swap ID
+    #[payable]
+    pub fn create_swap(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        wanted: SwapTarget,
+    ) -> U64 {
+        //enforce that the maker has enough storage for 1 EXTRA swap
+        let maker_id = env::predecessor_account_id();
+        self.internal_assert_storage(&maker_id, 1);
+
+        //make sure the wanted nft contract is a valid account
+        match &wanted {
+            SwapTarget::Token {
+                nft_contract_id, ..
+            }
+            | SwapTarget::Collection { nft_contract_id } => {
+                let _: AccountId = nft_contract_id.parse().expect("Invalid nft contract ID");
+            }
+        }
+
+        let near_amount = env::attached_deposit();
+        self.internal_lock_escrow(near_amount);
+
+        let swap_id = self.next_swap_id;
+        self.next_swap_id += 1;
+
+        self.internal_add_swap(Swap {
+            swap_id,
+            maker_id,
+            nft_contract_id: nft_contract_id.to_string(),
+            token_id,
+            approval_id: None,
+            near_amount: U128(near_amount),
+            wanted,
+        });
+
+        U64(swap_id)
+    }
+
+    //removes a swap offer and refunds the escrowed NEAR to the maker
+    #[payable]
+    pub fn remove_swap(&mut self, swap_id: U64) {
+        //assert that the user has attached exactly 1 yoctoNEAR (for security reasons)
+        assert_one_yocto();
+
+        let swap = self.internal_remove_swap(swap_id.0);
+        assert_eq!(
+            env::predecessor_account_id(),
+            swap.maker_id,
+            "Must be swap maker"
+        );
+
+        if swap.near_amount.0 > 0 {
+            self.internal_release_escrow(swap.near_amount.0);
+            Promise::new(swap.maker_id).transfer(swap.near_amount.0);
+        }
+    }
+
+    //private method used to resolve moving both tokens of a swap into the market's custody. If both arrived,
+    //each party gets the other's token and the taker gets the escrowed NEAR. Otherwise the token that did move
+    //goes back to its owner and the maker is refunded. Returns true if the swap went through
+    #[private]
+    pub fn resolve_swap_custody(
+        &mut self,
+        swap: Swap,
+        taker_id: AccountId,
+        taker_nft_contract_id: AccountId,
+        taker_token_id: String,
+    ) -> bool {
+        let maker_nft_contract_id: AccountId = swap.nft_contract_id.parse().unwrap();
+        let maker_token_moved = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        let taker_token_moved = matches!(env::promise_result(1), PromiseResult::Successful(_));
+
+        if !(maker_token_moved && taker_token_moved) {
+            //the escrowed NEAR is refunded to the maker
+            self.internal_release_escrow(swap.near_amount.0);
+            env::log_str("Not both tokens of the swap could be moved, rolling back");
+            if maker_token_moved {
+                self.internal_return_escrowed_token(
+                    maker_nft_contract_id,
+                    swap.token_id,
+                    swap.maker_id.clone(),
+                );
+            }
+            if taker_token_moved {
+                self.internal_return_escrowed_token(
+                    taker_nft_contract_id,
+                    taker_token_id,
+                    taker_id,
+                );
+            }
+            if swap.near_amount.0 > 0 {
+                Promise::new(swap.maker_id).transfer(swap.near_amount.0);
+            }
+            return false;
+        }
+
+        //both tokens are in the market's custody so each party gets the other's token. The escrowed NEAR
+        //stays locked until resolve_swap_delivery checked that both tokens arrived
+        ext_contract::nft_transfer(
+            taker_id.clone(),
+            swap.token_id.clone(),
+            None,
+            Some("swapped through market".to_string()),
+            maker_nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        .and(ext_contract::nft_transfer(
+            swap.maker_id.clone(),
+            taker_token_id.clone(),
+            None,
+            Some("swapped through market".to_string()),
+            taker_nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        ))
+        .then(ext_self::resolve_swap_delivery(
+            SwapDelivery {
+                maker_id: swap.maker_id,
+                maker_nft_contract_id,
+                maker_token_id: swap.token_id,
+                taker_id,
+                taker_nft_contract_id,
+                taker_token_id,
+                near_amount: swap.near_amount,
+            },
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_SWAP_DELIVERY,
+        ));
+
+        true
+    }
+
+    //private method used to resolve handing both tokens of a swap over. If both arrived, the taker is paid the
+    //escrowed NEAR. If neither did, both tokens go back to their owners and the maker is refunded. If only one
+    //arrived, the swap went through: the other token is recorded as claimable by its new owner and the taker is paid.
+    //returns true if the swap went through
+    #[private]
+    pub fn resolve_swap_delivery(&mut self, delivery: SwapDelivery) -> bool {
+        let maker_token_delivered = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        let taker_token_delivered = matches!(env::promise_result(1), PromiseResult::Successful(_));
+
+        //the escrowed NEAR is either paid to the taker or refunded to the maker from here on
+        self.internal_release_escrow(delivery.near_amount.0);
+
+        if !maker_token_delivered && !taker_token_delivered {
+            env::log_str("Neither token of the swap could be handed over, rolling back");
+            self.internal_return_escrowed_token(
+                delivery.maker_nft_contract_id,
+                delivery.maker_token_id,
+                delivery.maker_id.clone(),
+            );
+            self.internal_return_escrowed_token(
+                delivery.taker_nft_contract_id,
+                delivery.taker_token_id,
+                delivery.taker_id,
+            );
+            if delivery.near_amount.0 > 0 {
+                Promise::new(delivery.maker_id).transfer(delivery.near_amount.0);
+            }
+            return false;
+        }
+
+        //one party already got their token so the other token stays with the market for its new owner to claim
+        if !maker_token_delivered {
+            self.internal_record_claimable_token(
+                delivery.maker_nft_contract_id,
+                delivery.maker_token_id,
+                delivery.taker_id.clone(),
+            );
+        }
+        if !taker_token_delivered {
+            self.internal_record_claimable_token(
+                delivery.taker_nft_contract_id,
+                delivery.taker_token_id,
+                delivery.maker_id,
+            );
+        }
+
+        if delivery.near_amount.0 > 0 {
+            Promise::new(delivery.taker_id).transfer(delivery.near_amount.0);
+        }
+
+        true
+    }
+}
+
+impl Contract {
+    //internal method used when the maker approves their token for the market to open the swap
+    pub(crate) fn internal_activate_swap(
+        &mut self,
+        swap_id: u64,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+    ) {
+        let mut swap = self.swaps.get(&swap_id).expect("No swap");
+        assert_eq!(owner_id, swap.maker_id, "Must be swap maker");
+        assert!(
+            swap.nft_contract_id == nft_contract_id.to_string() && swap.token_id == token_id,
+            "Token is not offered in the swap"
+        );
+
+        swap.approval_id = Some(approval_id);
+        self.swaps.insert(&swap_id, &swap);
+    }
+
+    //internal method used when a taker approves a wanted token for the market to accept the swap.
+    //this will remove the swap and move both tokens into the market's custody
+    pub(crate) fn internal_accept_swap(
+        &mut self,
+        swap_id: u64,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        taker_id: AccountId,
+        approval_id: u64,
+    ) -> Promise {
+        let swap = self.swaps.get(&swap_id).expect("No swap");
+        let maker_approval_id = swap.approval_id.expect("Swap is not open yet");
+        assert_ne!(taker_id, swap.maker_id, "Cannot accept your own swap.");
+
+        //make sure the approved token is what the maker wants
+        let is_wanted = match &swap.wanted {
+            SwapTarget::Token {
+                nft_contract_id: wanted_contract_id,
+                token_id: wanted_token_id,
+            } => wanted_contract_id == nft_contract_id.as_str() && wanted_token_id == &token_id,
+            SwapTarget::Collection {
+                nft_contract_id: wanted_contract_id,
+            } => wanted_contract_id == nft_contract_id.as_str(),
+        };
+        assert!(is_wanted, "Token is not wanted by the swap");
+
+        //get the swap object by removing the swap. This makes sure it can only be accepted once
+        let swap = self.internal_remove_swap(swap_id);
+
+        //move both tokens into the market's custody at once
+        ext_contract::nft_transfer(
+            env::current_account_id(),
+            swap.token_id.clone(),
+            Some(maker_approval_id),
+            Some("swap held by market".to_string()),
+            swap.nft_contract_id.parse().unwrap(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        .and(ext_contract::nft_transfer(
+            env::current_account_id(),
+            token_id.clone(),
+            Some(approval_id),
+            Some("swap held by market".to_string()),
+            nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        ))
+        .then(ext_self::resolve_swap_custody(
+            swap,
+            taker_id,
+            nft_contract_id,
+            token_id,
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_SWAP,
+        ))
+    }
+
+    //internal method for adding a swap to the market and populating the indexes for the views
+    pub(crate) fn internal_add_swap(&mut self, swap: Swap) {
+        let maker_id = swap.maker_id.clone();
+        self.swaps.insert(&swap.swap_id, &swap);
+
+        //index the swap by the offered token, by the wanted token or collection and by the maker
+        let offered_token = format!("{}{}{}", swap.nft_contract_id, DELIMETER, swap.token_id);
+        self.internal_index_swap_by_token(&offered_token, swap.swap_id);
+        match &swap.wanted {
+            SwapTarget::Token {
+                nft_contract_id,
+                token_id,
+            } => {
+                let wanted_token = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+                self.internal_index_swap_by_token(&wanted_token, swap.swap_id);
+            }
+            SwapTarget::Collection { nft_contract_id } => {
+                let nft_contract_id: AccountId = nft_contract_id.parse().unwrap();
+                let mut swaps_by_nft_contract_id = self
+                    .swaps_by_nft_contract_id
+                    .get(&nft_contract_id)
+                    .unwrap_or_else(|| {
+                        UnorderedSet::new(
+                            StorageKey::SwapsByNFTContractIdInner {
+                                //we get a new unique prefix for the collection by hashing the nft contract
+                                account_id_hash: hash_account_id(&nft_contract_id),
+                            }
+                            .try_to_vec()
+                            .unwrap(),
+                        )
+                    });
+                swaps_by_nft_contract_id.insert(&swap.swap_id);
+                self.swaps_by_nft_contract_id
+                    .insert(&nft_contract_id, &swaps_by_nft_contract_id);
+            }
+        }
+
+        let mut swaps_by_maker_id = self.swaps_by_maker_id.get(&maker_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::SwapsByMakerIdInner {
+                    //we get a new unique prefix for the collection by hashing the maker
+                    account_id_hash: hash_account_id(&maker_id),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        swaps_by_maker_id.insert(&swap.swap_id);
+        self.swaps_by_maker_id.insert(&maker_id, &swaps_by_maker_id);
+    }
+
+    //internal method for removing a swap from the market. This returns the previously removed swap object
+    pub(crate) fn internal_remove_swap(&mut self, swap_id: u64) -> Swap {
+        let swap = self.swaps.remove(&swap_id).expect("No swap");
+
+        let offered_token = format!("{}{}{}", swap.nft_contract_id, DELIMETER, swap.token_id);
+        self.internal_unindex_swap_by_token(&offered_token, swap_id);
+        match &swap.wanted {
+            SwapTarget::Token {
+                nft_contract_id,
+                token_id,
+            } => {
+                let wanted_token = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+                self.internal_unindex_swap_by_token(&wanted_token, swap_id);
+            }
+            SwapTarget::Collection { nft_contract_id } => {
+                let nft_contract_id: AccountId = nft_contract_id.parse().unwrap();
+                let mut swaps_by_nft_contract_id = self
+                    .swaps_by_nft_contract_id
+                    .get(&nft_contract_id)
+                    .expect("No swap by nft_contract_id");
+                swaps_by_nft_contract_id.remove(&swap_id);
+                if swaps_by_nft_contract_id.is_empty() {
+                    self.swaps_by_nft_contract_id.remove(&nft_contract_id);
+                } else {
+                    self.swaps_by_nft_contract_id
+                        .insert(&nft_contract_id, &swaps_by_nft_contract_id);
+                }
+            }
+        }
+
+        let mut swaps_by_maker_id = self
+            .swaps_by_maker_id
+            .get(&swap.maker_id)
+            .expect("No swap by maker_id");
+        swaps_by_maker_id.remove(&swap_id);
+        if swaps_by_maker_id.is_empty() {
+            self.swaps_by_maker_id.remove(&swap.maker_id);
+        } else {
+            self.swaps_by_maker_id
+                .insert(&swap.maker_id, &swaps_by_maker_id);
+        }
+
+        swap
+    }
+
+    //adds a swap to the set of swaps a token is offered or wanted in
+    fn internal_index_swap_by_token(
+        &mut self,
+        contract_and_token_id: &ContractAndTokenId,
+        swap_id: u64,
+    ) {
+        let mut swaps_by_token = self
+            .swaps_by_token
+            .get(contract_and_token_id)
+            .unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::SwapsByTokenInner {
+                        //we get a new unique prefix for the collection by hashing the unique token ID
+                        token_hash: hash_contract_and_token_id(contract_and_token_id),
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                )
+            });
+        swaps_by_token.insert(&swap_id);
+        self.swaps_by_token
+            .insert(contract_and_token_id, &swaps_by_token);
+    }
+
+    //removes a swap from the set of swaps a token is offered or wanted in
+    fn internal_unindex_swap_by_token(
+        &mut self,
+        contract_and_token_id: &ContractAndTokenId,
+        swap_id: u64,
+    ) {
+        let mut swaps_by_token = self
+            .swaps_by_token
+            .get(contract_and_token_id)
+            .expect("No swap by token");
+        swaps_by_token.remove(&swap_id);
+        if swaps_by_token.is_empty() {
+            self.swaps_by_token.remove(contract_and_token_id);
+        } else {
+            self.swaps_by_token
+                .insert(contract_and_token_id, &swaps_by_token);
+        }
+    }
+}
+
+//this is the cross contract call that we call on our own contract.
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn resolve_swap_custody(
+        &mut self,
+        swap: Swap,
+        taker_id: AccountId,
+        taker_nft_contract_id: AccountId,
+        taker_token_id: String,
+    ) -> bool;
+
+    fn resolve_swap_delivery(&mut self, delivery: SwapDelivery) -> bool;
+}
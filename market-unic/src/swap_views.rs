@@ -0,0 +1,110 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// views
+    //returns the number of swaps the marketplace holds (as a string)
+    pub fn get_supply_swaps(&self) -> U64 {
+        //returns the swaps object length wrapped as a U64
+        U64(self.swaps.len())
+    }
+
+    //returns paginated open swaps a given token is offered or wanted in. (result is a vector of swaps)
+    pub fn get_swaps_by_token(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: String,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Swap> {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        self.internal_get_open_swaps(
+            self.swaps_by_token.get(&contract_and_token_id),
+            from_index,
+            limit,
+        )
+    }
+
+    //returns paginated open swaps that accept any token of a given collection. (result is a vector of swaps)
+    pub fn get_swaps_by_nft_contract_id(
+        &self,
+        nft_contract_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Swap> {
+        self.internal_get_open_swaps(
+            self.swaps_by_nft_contract_id.get(&nft_contract_id),
+            from_index,
+            limit,
+        )
+    }
+
+    //returns paginated swaps made by a given account, open or not. (result is a vector of swaps)
+    pub fn get_swaps_by_maker_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Swap> {
+        //get the set of swap IDs for the given maker
+        let swaps_by_maker_id =
+            if let Some(swaps_by_maker_id) = self.swaps_by_maker_id.get(&account_id) {
+                swaps_by_maker_id
+            } else {
+                return vec![];
+            };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        swaps_by_maker_id
+            .as_vector()
+            .iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //we'll map the swap IDs into Swap objects
+            .map(|swap_id| self.swaps.get(&swap_id).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //get a swap by its ID
+    pub fn get_swap(&self, swap_id: U64) -> Option<Swap> {
+        self.swaps.get(&swap_id.0)
+    }
+}
+
+impl Contract {
+    //returns the paginated swaps of a set that are open (the maker approved the market for their token)
+    fn internal_get_open_swaps(
+        &self,
+        swap_ids: Option<UnorderedSet<u64>>,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Swap> {
+        let swap_ids = if let Some(swap_ids) = swap_ids {
+            swap_ids
+        } else {
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        swap_ids
+            .as_vector()
+            .iter()
+            //we'll map the swap IDs into Swap objects
+            .map(|swap_id| self.swaps.get(&swap_id).unwrap())
+            //only keep the swaps the maker approved the market for
+            .filter(|swap| swap.approval_id.is_some())
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector. If we didn't specify a limit, use 0
+            .take(limit.unwrap_or(0) as usize)
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+}
@@ -16,8 +16,8 @@ pub trait NonFungibleTokenCore {
         approval_id: Option<u64>,
     ) -> bool;
 
-    //revoke a specific account from transferring the token on your behalf
-    fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId); 
+    //revoke a specific account from transferring the token on your behalf (or give up your own approval)
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId);
 
     //revoke all accounts from transferring the token on your behalf
     fn nft_revoke_all(&mut self, token_id: TokenId);
@@ -33,6 +33,77 @@ trait NonFungibleTokenApprovalsReceiver {
         approval_id: u64,
         msg: String,
     );
+
+    //cross contract call to an external contract that is initiated during nft_batch_approve
+    fn nft_on_batch_approve(
+        &mut self,
+        approvals: Vec<(TokenId, u64)>,
+        owner_id: AccountId,
+        msg: String,
+    );
+}
+
+#[near_bindgen]
+impl Contract {
+    //approve an account ID to transfer several tokens on your behalf. All approvals are charged
+    //for storage at once and the receiver is notified with a single cross contract call
+    #[payable]
+    pub fn nft_batch_approve(
+        &mut self,
+        token_ids: Vec<TokenId>,
+        account_id: AccountId,
+        msg: Option<String>,
+    ) {
+        assert_at_least_one_yocto();
+        assert!(!token_ids.is_empty(), "Must approve at least one token");
+
+        let owner_id = env::predecessor_account_id();
+
+        //keep track of the (token ID, approval ID) pairs and the total storage used by the new approvals
+        let mut approvals = Vec::with_capacity(token_ids.len());
+        let mut storage_used: u64 = 0;
+
+        for token_id in token_ids {
+            let mut token = self.tokens_by_id.get(&token_id).expect("No token");
+
+            assert_eq!(
+                &owner_id, &token.owner_id,
+                "Predecessor must be the token owner."
+            );
+            assert_not_in_live_auction(&token_id);
+
+            let approval_id: u64 = token.next_approval_id;
+
+            let is_new_approval = token
+                .approved_account_ids
+                .insert(account_id.clone(), approval_id)
+                .is_none();
+
+            if is_new_approval {
+                storage_used += bytes_for_approved_account_id(&account_id);
+            }
+
+            token.next_approval_id += 1;
+            self.tokens_by_id.insert(&token_id, &token);
+
+            approvals.push((token_id, approval_id));
+        }
+
+        //charge the storage for every new approval in one go
+        refund_deposit(storage_used);
+
+        if let Some(msg) = msg {
+            ext_non_fungible_approval_receiver::nft_on_batch_approve(
+                approvals,
+                owner_id,
+                msg,
+                account_id,                               //contract account we're calling
+                NO_DEPOSIT,                               //NEAR deposit we attach to the call
+                env::prepaid_gas() - GAS_FOR_NFT_APPROVE, //GAS we're attaching
+            )
+            .as_return(); // Returning this promise
+        }
+    }
 }
 
 #[near_bindgen]
@@ -49,6 +120,7 @@ impl NonFungibleTokenCore for Contract {
             &token.owner_id,
             "Predecessor must be the token owner."
         );
+        assert_not_in_live_auction(&token_id);
 
         let approval_id: u64 = token.next_approval_id;
 
@@ -110,21 +182,25 @@ impl NonFungibleTokenCore for Contract {
         }
     }
 
-    //revoke a specific account from transferring the token on your behalf
+    //revoke a specific account from transferring the token on your behalf. The approved account (for example
+    //a market taking down a listing) can also give up its own approval
     #[payable]
     fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
         assert_one_yocto();
         //get the token object using the passed in token_id
         let mut token = self.tokens_by_id.get(&token_id).expect("No token");
 
-        //get the caller of the function and assert that they are the owner of the token
+        //get the caller of the function and assert that they are the owner of the token or the approved account
         let predecessor_account_id = env::predecessor_account_id();
-        assert_eq!(&predecessor_account_id, &token.owner_id);
+        assert!(
+            predecessor_account_id == token.owner_id || predecessor_account_id == account_id,
+            "Only the token owner or the approved account can revoke the approval"
+        );
 
         //if the account ID was in the token's approval, we remove it and the if statement logic executes
         if token.approved_account_ids.remove(&account_id).is_some() {
-            //refund the funds released by removing the approved_account_id to the caller of the function
-            refund_approved_account_ids_iter(predecessor_account_id, [account_id].iter());
+            //refund the funds released by removing the approved_account_id to the token owner who paid for it
+            refund_approved_account_ids_iter(token.owner_id.clone(), [account_id].iter());
 
             //insert the token back into the tokens_by_id collection with the account_id removed from the approval list
             self.tokens_by_id.insert(&token_id, &token);
@@ -25,6 +25,17 @@ pub trait NonFungibleTokenCore {
     fn nft_auction_revoke(&mut self, token_id: TokenId, account_id: AccountId);
 
     fn nft_revoke_all_auctions(&mut self, token_id: TokenId);
+
+    //cancel an auction for the given token, unlocking it for transfers and approvals
+    fn nft_cancel_auction(&mut self, token_id: TokenId, account_id: AccountId);
+
+    //check if the auction with the given ID is still live for the passed in account
+    fn nft_auction_is_live(
+        &self,
+        token_id: TokenId,
+        account_id: AccountId,
+        auction_id: u64,
+    ) -> bool;
 }
 
 #[ext_contract(ext_nft_auction_receiver)]
@@ -61,11 +72,21 @@ impl NonFungibleTokenCore for Contract {
             &token.owner_id,
             "Predecessor must be the token owner."
         );
-        /*assert_eq!(
-            self.auctioned_tokens.contains(&auction_token),
-            false,
-            "Already auctioned"
-        ); */
+        //a token can only be in one auction at a time. An earlier auction approval must be used, revoked or expired first
+        let has_pending_auction = auction_windows()
+            .get(&auction_token)
+            .map(|windows| {
+                windows
+                    .values()
+                    .any(|window| !auction_window_is_expired(window))
+            })
+            .unwrap_or(false);
+        assert!(
+            !has_pending_auction,
+            "Token is already in a pending or live auction"
+        );
+
+        assert!(end_time > start_time, "Auction must end after it starts");
 
         let auction_id: u64 = token.auction_list_id;
 
@@ -74,8 +95,23 @@ impl NonFungibleTokenCore for Contract {
             .insert(account_id.clone(), auction_id)
             .is_none();
 
+        //record the auction window (in nanoseconds) so the auction approval is scoped to it
+        let window = AuctionWindow {
+            start_time: start_time
+                .checked_mul(1_000_000_000)
+                .expect("Auction start time is too large"),
+            //the settlement period is added to the end time when the approval is used so it must fit as well
+            end_time: end_time
+                .checked_mul(1_000_000_000)
+                .filter(|end_time| end_time.checked_add(AUCTION_SETTLEMENT_PERIOD).is_some())
+                .expect("Auction end time is too large"),
+        };
+        let mut windows = auction_windows().get(&auction_token).unwrap_or_default();
+        windows.insert(account_id.clone(), window);
+        auction_windows().insert(&auction_token, &windows);
+
         let storage_used = if is_new_auction {
-            bytes_for_approved_account_id(&account_id)
+            bytes_for_approved_auction_account_id(&account_id)
         } else {
             0
         };
@@ -129,20 +165,24 @@ impl NonFungibleTokenCore for Contract {
         }
     }
 
+    //revoke an auction approval. The approved account can always give up its approval. The owner can only
+    //revoke it before the auction starts or once the approval expired
     #[payable]
     fn nft_auction_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
         assert_one_yocto();
         //get the token object using the passed in token_id
         let mut token = self.tokens_by_id.get(&token_id).expect("No token");
 
-        //get the caller of the function and assert that they are the owner of the token
-        let predecessor_account_id = env::predecessor_account_id();
-        assert_eq!(&predecessor_account_id, &token.owner_id);
+        //make sure the caller can remove the auction approval
+        assert_can_remove_auction_approval(&token, &token_id, &account_id);
 
         //if the account ID was in the token's approval, we remove it and the if statement logic executes
         if token.auctions_by_owner.remove(&account_id).is_some() {
-            //refund the funds released by removing the approved_account_id to the caller of the function
-            refund_approved_auction_account_ids_iter(predecessor_account_id, [account_id].iter());
+            //the auction window is only meaningful alongside the auction approval
+            remove_auction_window(&token_id, &account_id);
+
+            //refund the funds released by removing the approved_account_id to the token owner who paid for them
+            refund_approved_auction_account_ids_iter(token.owner_id.clone(), [account_id].iter());
 
             //insert the token back into the tokens_by_id collection with the account_id removed from the auction approval list
             self.tokens_by_id.insert(&token_id, &token);
@@ -159,6 +199,8 @@ impl NonFungibleTokenCore for Contract {
         //get the caller and make sure they are the owner of the tokens
         let predecessor_account_id = env::predecessor_account_id();
         assert_eq!(&predecessor_account_id, &token.owner_id);
+        //bidders are waiting on a live auction so its approval can't be revoked
+        assert_not_in_live_auction(&token_id);
 
         //only revoke if the approved account IDs for the token is not empty
         if !token.auctions_by_owner.is_empty() {
@@ -166,8 +208,63 @@ impl NonFungibleTokenCore for Contract {
             refund_approved_auction_account_ids(predecessor_account_id, &token.auctions_by_owner);
             //clear the approved auction account IDs
             token.auctions_by_owner.clear();
+            auction_windows().remove(&token_id);
             //insert the token back into the tokens_by_id collection with the approved account IDs cleared
             self.tokens_by_id.insert(&token_id, &token);
         }
     }
+
+    //cancel an auction. The auction approval is removed so the market can no longer settle it
+    //and the market can check the outcome through nft_auction_is_live. The owner can only cancel
+    //an auction before it starts (or once its approval expired), the market can cancel it at any time
+    #[payable]
+    fn nft_cancel_auction(&mut self, token_id: TokenId, account_id: AccountId) {
+        assert_one_yocto();
+        //get the token object using the passed in token_id
+        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
+
+        //make sure the caller can remove the auction approval
+        assert_can_remove_auction_approval(&token, &token_id, &account_id);
+
+        let auction_id = token
+            .auctions_by_owner
+            .remove(&account_id)
+            .expect("No live auction for the given account");
+        remove_auction_window(&token_id, &account_id);
+
+        //refund the storage released by removing the auction approval to the token owner who paid for it
+        refund_approved_auction_account_ids_iter(
+            token.owner_id.clone(),
+            [account_id.clone()].iter(),
+        );
+
+        self.tokens_by_id.insert(&token_id, &token);
+
+        env::log_str(&format!(
+            "Auction {} for token {} on {} cancelled by {}",
+            auction_id,
+            token_id,
+            account_id,
+            env::predecessor_account_id()
+        ));
+    }
+
+    //check if the auction with the given ID is still live for the passed in account
+    fn nft_auction_is_live(
+        &self,
+        token_id: TokenId,
+        account_id: AccountId,
+        auction_id: u64,
+    ) -> bool {
+        //if the token doesn't exist anymore, there is no live auction for it
+        if let Some(token) = self.tokens_by_id.get(&token_id) {
+            //an expired auction approval can no longer settle the auction so it isn't live either
+            token.auctions_by_owner.get(&account_id) == Some(&auction_id)
+                && auction_window(&token_id, &account_id)
+                    .map(|window| !auction_window_is_expired(&window))
+                    .unwrap_or(false)
+        } else {
+            false
+        }
+    }
 }
@@ -2,12 +2,16 @@ use crate::*;
 use near_sdk::CryptoHash;
 use std::mem::size_of;
 
+//the market settles an auction after it ends, so an auction approval stays usable for this long (in nanoseconds) after the end time
+pub(crate) const AUCTION_SETTLEMENT_PERIOD: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+
 pub(crate) fn bytes_for_approved_account_id(account_id: &AccountId) -> u64 {
     account_id.as_str().len() as u64 + 4 + size_of::<u64>() as u64
 }
 
+//an auction approval stores both the auction ID and the auction window for the account
 pub(crate) fn bytes_for_approved_auction_account_id(account_id: &AccountId) -> u64 {
-    account_id.as_str().len() as u64 + 4 + size_of::<u64>() as u64
+    2 * (account_id.as_str().len() as u64 + 4) + 3 * size_of::<u64>() as u64
 }
 
 //refund the storage taken up by passed in approved account IDs and send the funds to the passed in account ID.
@@ -56,8 +60,8 @@ pub(crate) fn refund_approved_auction_account_ids(
     account_id: AccountId,
     auctions_by_owner: &HashMap<AccountId, u64>,
 ) -> Promise {
-    //call the refund_approved_account_ids_iter with the approved account IDs as keys
-    refund_approved_account_ids_iter(account_id, auctions_by_owner.keys())
+    //call the refund_approved_auction_account_ids_iter with the approved auction account IDs as keys
+    refund_approved_auction_account_ids_iter(account_id, auctions_by_owner.keys())
 }
 
 //used to generate a unique prefix in our storage collections (this is to avoid data collisions)
@@ -107,6 +111,110 @@ pub(crate) fn refund_deposit(storage_used: u64) {
     }
 }
 
+//the auction windows of every token (token ID -> approved account -> window). They are kept in their own map
+//so the layout of the tokens that were stored before auction windows existed doesn't change
+pub(crate) fn auction_windows() -> LookupMap<TokenId, HashMap<AccountId, AuctionWindow>> {
+    LookupMap::new(StorageKey::AuctionWindows.try_to_vec().unwrap())
+}
+
+//get the auction window of the given approved account for a token
+pub(crate) fn auction_window(token_id: &TokenId, account_id: &AccountId) -> Option<AuctionWindow> {
+    auction_windows()
+        .get(token_id)
+        .and_then(|windows| windows.get(account_id).cloned())
+}
+
+//remove the auction window of the given approved account for a token
+pub(crate) fn remove_auction_window(token_id: &TokenId, account_id: &AccountId) {
+    let mut windows = auction_windows();
+    if let Some(mut token_windows) = windows.get(token_id) {
+        token_windows.remove(account_id);
+        if token_windows.is_empty() {
+            windows.remove(token_id);
+        } else {
+            windows.insert(token_id, &token_windows);
+        }
+    }
+}
+
+//make sure an auction approval can be used to transfer the token. The auction approval must match the passed in
+//auction ID and is only valid from the start of the auction until the settlement period after its end is over
+pub(crate) fn assert_auction_approval(
+    token: &Token,
+    token_id: &TokenId,
+    sender_id: &AccountId,
+    auction_id: Option<u64>,
+) {
+    let actual_auction_id = token
+        .auctions_by_owner
+        .get(sender_id)
+        .expect("Sender is not approved account");
+
+    if let Some(enforced_auction_id) = auction_id {
+        assert_eq!(
+            actual_auction_id, &enforced_auction_id,
+            "The actual auction id {} is different from the given auction id {}",
+            actual_auction_id, enforced_auction_id,
+        );
+    }
+
+    let window =
+        auction_window(token_id, sender_id).expect("No auction window for the approved account");
+
+    assert!(
+        env::block_timestamp() >= window.start_time,
+        "The auction has not started yet"
+    );
+    assert!(
+        env::block_timestamp() < window.end_time + AUCTION_SETTLEMENT_PERIOD,
+        "The auction approval has expired"
+    );
+}
+
+//checks if an auction approval can no longer be used because the settlement period after the auction is over
+pub(crate) fn auction_window_is_expired(window: &AuctionWindow) -> bool {
+    env::block_timestamp() >= window.end_time + AUCTION_SETTLEMENT_PERIOD
+}
+
+//checks if an auction window locks the token. A token is locked from the start of the auction
+//until its approval is consumed by the settlement, revoked by the market or expires
+pub(crate) fn auction_window_is_live(window: &AuctionWindow) -> bool {
+    env::block_timestamp() >= window.start_time && !auction_window_is_expired(window)
+}
+
+//make sure the token is not locked in a live auction
+pub(crate) fn assert_not_in_live_auction(token_id: &TokenId) {
+    let is_live = auction_windows()
+        .get(token_id)
+        .map(|windows| windows.values().any(auction_window_is_live))
+        .unwrap_or(false);
+    assert!(!is_live, "Token is locked in a live auction");
+}
+
+//make sure an auction approval can be removed by the caller. The approved account (the market) can always give up
+//its approval, while the owner can only cancel an auction that hasn't started yet or whose approval expired
+pub(crate) fn assert_can_remove_auction_approval(
+    token: &Token,
+    token_id: &TokenId,
+    account_id: &AccountId,
+) {
+    let predecessor_account_id = env::predecessor_account_id();
+    if &predecessor_account_id == account_id {
+        return;
+    }
+
+    assert_eq!(
+        predecessor_account_id, token.owner_id,
+        "Only the token owner or the approved account can remove the auction"
+    );
+    if let Some(window) = auction_window(token_id, account_id) {
+        assert!(
+            !auction_window_is_live(&window),
+            "Token is locked in a live auction"
+        );
+    }
+}
+
 pub(crate) fn royalty_to_payout(royalty_percentage: u32, amount_to_pay: Balance) -> U128 {
     U128(royalty_percentage as u128 * amount_to_pay / 10_000u128)
 }
@@ -167,22 +275,41 @@ impl Contract {
     ) -> Token {
         let token = self.tokens_by_id.get(token_id).expect("No Token");
 
-        if sender_id != &token.owner_id {
-            if !token.approved_account_ids.contains_key(sender_id) {
-                env::panic_str("Unauthorized");
-            }
+        if sender_id == &token.owner_id {
+            //the owner can't move the token away while bidders are waiting on the auction
+            assert_not_in_live_auction(token_id);
+        } else {
+            //check if the sender has a regular approval matching the passed in approval ID
+            let has_matching_approval = match approval_id {
+                Some(approval_id) => {
+                    token.approved_account_ids.get(sender_id) == Some(&approval_id)
+                }
+                None => token.approved_account_ids.contains_key(sender_id),
+            };
+
+            //if not, the sender may still be authorized through an auction approval
+            if !has_matching_approval && token.auctions_by_owner.contains_key(sender_id) {
+                assert_auction_approval(&token, token_id, sender_id, approval_id);
+            } else {
+                //a live auction can only be settled through its auction approval
+                assert_not_in_live_auction(token_id);
+
+                if !token.approved_account_ids.contains_key(sender_id) {
+                    env::panic_str("Unauthorized");
+                }
 
-            if let Some(enforced_approval_id) = approval_id {
-                let actual_approval_id = token
-                    .approved_account_ids
-                    .get(sender_id)
-                    .expect("Sender is not approved account");
-
-                assert_eq!(
-                    actual_approval_id, &enforced_approval_id,
-                    "The actual approval id {} is different from the given approval_id {}",
-                    actual_approval_id, enforced_approval_id,
-                );
+                if let Some(enforced_approval_id) = approval_id {
+                    let actual_approval_id = token
+                        .approved_account_ids
+                        .get(sender_id)
+                        .expect("Sender is not approved account");
+
+                    assert_eq!(
+                        actual_approval_id, &enforced_approval_id,
+                        "The actual approval id {} is different from the given approval_id {}",
+                        actual_approval_id, enforced_approval_id,
+                    );
+                }
             }
         }
 
@@ -201,8 +328,11 @@ impl Contract {
             next_approval_id: token.next_approval_id,
             royalty: token.royalty.clone(),
             auction_list_id: token.auction_list_id,
+            //the auction approvals (including the one used to settle an auction) are consumed by the transfer
             auctions_by_owner: Default::default(),
         };
+        //the auction windows go away with the auction approvals
+        auction_windows().remove(token_id);
 
         self.tokens_by_id.insert(token_id, &new_token);
 
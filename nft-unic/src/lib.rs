@@ -26,6 +26,9 @@ mod mint;
 mod nft_core;
 mod royalty;
 
+#[cfg(test)]
+mod tests;
+
 /// This spec can be treated like a version of the standard.
 pub const NFT_METADATA_SPEC: &str = "1.0.0";
 /// This is the name of the NFT standard we're using
@@ -53,6 +56,7 @@ pub enum StorageKey {
     TokensPerTypeInner { token_type_hash: CryptoHash },
     TokenTypesLocked,
     AuctionedTokens,
+    AuctionWindows,
 }
 
 #[near_bindgen]
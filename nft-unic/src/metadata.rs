@@ -46,6 +46,15 @@ pub struct Token {
     pub auctions_by_owner: HashMap<AccountId, u64>,
 }
 
+//the time window (in nanoseconds) of an auction approved through approve_nft_auction.
+//the windows are stored per token in their own map (see auction_windows) rather than in the token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionWindow {
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
 //The Json token is what will be returned from view calls.
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -101,6 +101,10 @@ impl NonFungibleTokenCore for Contract {
             previous_token.owner_id.clone(),
             &previous_token.approved_account_ids,
         );
+        refund_approved_auction_account_ids(
+            previous_token.owner_id.clone(),
+            &previous_token.auctions_by_owner,
+        );
     }
 
     //implementation of the transfer call method. This will transfer the NFT and call a method on the reciver_id contract
@@ -133,6 +137,12 @@ impl NonFungibleTokenCore for Contract {
             memo.clone(),
         );
 
+        //auction approvals are not restored if the transfer is reverted so we refund them right away
+        refund_approved_auction_account_ids(
+            previous_token.owner_id.clone(),
+            &previous_token.auctions_by_owner,
+        );
+
         let mut authorized_id = None;
 
         if sender_id != previous_token.owner_id {
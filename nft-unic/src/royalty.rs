@@ -81,6 +81,11 @@ impl NonFungibleTokenCore for Contract {
             previous_token.owner_id.clone(),
             &previous_token.approved_account_ids,
         );
+        //refund the previous token owner for the storage used up by the auction approvals (consumed by the transfer)
+        refund_approved_auction_account_ids(
+            previous_token.owner_id.clone(),
+            &previous_token.auctions_by_owner,
+        );
 
         //get the owner of the token
         let owner_id = previous_token.owner_id;
@@ -0,0 +1,231 @@
+use crate::approval::NonFungibleTokenCore as _;
+use crate::auction::NonFungibleTokenCore as _;
+use crate::nft_core::NonFungibleTokenCore as _;
+use crate::royalty::NonFungibleTokenCore as _;
+use crate::*;
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+
+const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+const ONE_SECOND: u64 = 1_000_000_000;
+const TOKEN_ID: &str = "token-1";
+
+//alice owns the token, bob buys it, charlie gets royalties and the market is approved for the auction
+fn market() -> AccountId {
+    accounts(3)
+}
+
+fn set_context(predecessor: AccountId, deposit: Balance, block_timestamp: u64) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id(accounts(5))
+        .predecessor_account_id(predecessor.clone())
+        .signer_account_id(predecessor)
+        .attached_deposit(deposit)
+        .block_timestamp(block_timestamp)
+        .build());
+}
+
+fn token_metadata() -> TokenMetadata {
+    TokenMetadata {
+        title: Some("Token".to_string()),
+        description: None,
+        media: None,
+        media_hash: None,
+        copies: None,
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+    }
+}
+
+//mint a token for alice with a 10% royalty for charlie and approve the market for an auction
+//running from second 100 to second 200
+fn setup_auction() -> Contract {
+    set_context(accounts(0), ONE_NEAR, 0);
+    let mut contract = Contract::new_default_meta(accounts(0));
+
+    let mut royalties = HashMap::new();
+    royalties.insert(accounts(2), 1_000);
+    contract.nft_mint(
+        TOKEN_ID.to_string(),
+        token_metadata(),
+        accounts(0),
+        Some(royalties),
+    );
+
+    set_context(accounts(0), ONE_NEAR / 100, 0);
+    contract.approve_nft_auction(TOKEN_ID.to_string(), market(), 100, 200, None);
+    contract
+}
+
+#[test]
+fn auction_approval_transfers_through_nft_transfer_payout() {
+    let mut contract = setup_auction();
+    assert!(contract.auction_is_approved(TOKEN_ID.to_string(), market(), Some(0)));
+
+    set_context(market(), 1, 150 * ONE_SECOND);
+    let payout =
+        contract.nft_transfer_payout(accounts(1), TOKEN_ID.to_string(), 0, None, U128(1_000), 10);
+
+    assert_eq!(payout.payout.get(&accounts(0)), Some(&U128(900)));
+    assert_eq!(payout.payout.get(&accounts(2)), Some(&U128(100)));
+
+    let token = contract.tokens_by_id.get(&TOKEN_ID.to_string()).unwrap();
+    assert_eq!(token.owner_id, accounts(1));
+    //the auction approval and its window are consumed by the transfer
+    assert!(token.auctions_by_owner.is_empty());
+    assert!(auction_window(&TOKEN_ID.to_string(), &market()).is_none());
+}
+
+#[test]
+fn auction_approval_can_settle_after_the_auction_ends() {
+    let mut contract = setup_auction();
+
+    set_context(
+        market(),
+        1,
+        200 * ONE_SECOND + AUCTION_SETTLEMENT_PERIOD - 1,
+    );
+    contract.nft_transfer_payout(accounts(1), TOKEN_ID.to_string(), 0, None, U128(1_000), 10);
+
+    let token = contract.tokens_by_id.get(&TOKEN_ID.to_string()).unwrap();
+    assert_eq!(token.owner_id, accounts(1));
+}
+
+#[test]
+#[should_panic(expected = "The auction has not started yet")]
+fn auction_approval_is_not_valid_before_the_auction_starts() {
+    let mut contract = setup_auction();
+
+    set_context(market(), 1, 99 * ONE_SECOND);
+    contract.nft_transfer_payout(accounts(1), TOKEN_ID.to_string(), 0, None, U128(1_000), 10);
+}
+
+#[test]
+#[should_panic(expected = "The auction approval has expired")]
+fn auction_approval_expires_after_the_settlement_period() {
+    let mut contract = setup_auction();
+
+    set_context(market(), 1, 200 * ONE_SECOND + AUCTION_SETTLEMENT_PERIOD);
+    contract.nft_transfer_payout(accounts(1), TOKEN_ID.to_string(), 0, None, U128(1_000), 10);
+}
+
+#[test]
+#[should_panic(expected = "is different from the given auction id")]
+fn auction_approval_must_match_the_auction_id() {
+    let mut contract = setup_auction();
+
+    set_context(market(), 1, 150 * ONE_SECOND);
+    contract.nft_transfer_payout(accounts(1), TOKEN_ID.to_string(), 1, None, U128(1_000), 10);
+}
+
+#[test]
+#[should_panic(expected = "Auction end time is too large")]
+fn approve_nft_auction_rejects_times_that_overflow() {
+    set_context(accounts(0), ONE_NEAR, 0);
+    let mut contract = Contract::new_default_meta(accounts(0));
+    contract.nft_mint(TOKEN_ID.to_string(), token_metadata(), accounts(0), None);
+
+    set_context(accounts(0), ONE_NEAR / 100, 0);
+    contract.approve_nft_auction(TOKEN_ID.to_string(), market(), 100, u64::MAX / 2, None);
+}
+
+#[test]
+#[should_panic(expected = "Token is locked in a live auction")]
+fn owner_cannot_cancel_a_live_auction() {
+    let mut contract = setup_auction();
+
+    set_context(accounts(0), 1, 150 * ONE_SECOND);
+    contract.nft_cancel_auction(TOKEN_ID.to_string(), market());
+}
+
+#[test]
+#[should_panic(expected = "Token is locked in a live auction")]
+fn owner_cannot_revoke_a_live_auction() {
+    let mut contract = setup_auction();
+
+    set_context(accounts(0), 1, 150 * ONE_SECOND);
+    contract.nft_auction_revoke(TOKEN_ID.to_string(), market());
+}
+
+#[test]
+#[should_panic(expected = "Token is locked in a live auction")]
+fn owner_cannot_revoke_all_auctions_during_a_live_auction() {
+    let mut contract = setup_auction();
+
+    set_context(accounts(0), 1, 150 * ONE_SECOND);
+    contract.nft_revoke_all_auctions(TOKEN_ID.to_string());
+}
+
+#[test]
+#[should_panic(expected = "Token is locked in a live auction")]
+fn owner_cannot_transfer_a_token_in_a_live_auction() {
+    let mut contract = setup_auction();
+
+    set_context(accounts(0), 1, 150 * ONE_SECOND);
+    contract.nft_transfer(accounts(1), TOKEN_ID.to_string(), None, None);
+}
+
+#[test]
+fn owner_can_cancel_an_auction_before_it_starts() {
+    let mut contract = setup_auction();
+
+    set_context(accounts(0), 1, 50 * ONE_SECOND);
+    contract.nft_cancel_auction(TOKEN_ID.to_string(), market());
+
+    assert!(!contract.nft_auction_is_live(TOKEN_ID.to_string(), market(), 0));
+    assert!(auction_window(&TOKEN_ID.to_string(), &market()).is_none());
+}
+
+#[test]
+fn market_can_give_up_a_live_auction() {
+    let mut contract = setup_auction();
+
+    set_context(market(), 1, 150 * ONE_SECOND);
+    contract.nft_auction_revoke(TOKEN_ID.to_string(), market());
+
+    //the token is unlocked so the owner can transfer it again
+    set_context(accounts(0), 1, 150 * ONE_SECOND);
+    contract.nft_transfer(accounts(1), TOKEN_ID.to_string(), None, None);
+    let token = contract.tokens_by_id.get(&TOKEN_ID.to_string()).unwrap();
+    assert_eq!(token.owner_id, accounts(1));
+}
+
+#[test]
+fn owner_can_transfer_once_the_auction_approval_expired() {
+    let mut contract = setup_auction();
+
+    set_context(accounts(0), 1, 200 * ONE_SECOND + AUCTION_SETTLEMENT_PERIOD);
+    assert!(!contract.nft_auction_is_live(TOKEN_ID.to_string(), market(), 0));
+    contract.nft_transfer(accounts(1), TOKEN_ID.to_string(), None, None);
+
+    let token = contract.tokens_by_id.get(&TOKEN_ID.to_string()).unwrap();
+    assert_eq!(token.owner_id, accounts(1));
+}
+
+#[test]
+fn approved_account_can_revoke_its_own_approval() {
+    let mut contract = setup_auction();
+    set_context(accounts(0), ONE_NEAR / 100, 0);
+    contract.nft_approve(TOKEN_ID.to_string(), accounts(4), None);
+    assert!(contract.nft_is_approved(TOKEN_ID.to_string(), accounts(4), None));
+
+    set_context(accounts(4), 1, 0);
+    contract.nft_revoke(TOKEN_ID.to_string(), accounts(4));
+    assert!(!contract.nft_is_approved(TOKEN_ID.to_string(), accounts(4), None));
+}
+
+#[test]
+#[should_panic(expected = "Only the token owner or the approved account can revoke the approval")]
+fn other_accounts_cannot_revoke_an_approval() {
+    let mut contract = setup_auction();
+    set_context(accounts(0), ONE_NEAR / 100, 0);
+    contract.nft_approve(TOKEN_ID.to_string(), accounts(4), None);
+
+    set_context(accounts(1), 1, 0);
+    contract.nft_revoke(TOKEN_ID.to_string(), accounts(4));
+}